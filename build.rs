@@ -50,6 +50,35 @@ fn main() {
             .entry("Enter", "\"CR\"")
             .entry("Tab", "\"Tab\"")
             .entry("ISO_Left_Tab", "\"Tab\"")
+            .entry("KP_Enter", "\"kEnter\"")
+            .entry("KP_Add", "\"kPlus\"")
+            .entry("KP_Subtract", "\"kMinus\"")
+            .entry("KP_Multiply", "\"kMultiply\"")
+            .entry("KP_Divide", "\"kDivide\"")
+            .entry("KP_Decimal", "\"kPoint\"")
+            .entry("KP_Separator", "\"kComma\"")
+            .entry("KP_Equal", "\"kEqual\"")
+            .entry("KP_0", "\"k0\"")
+            .entry("KP_1", "\"k1\"")
+            .entry("KP_2", "\"k2\"")
+            .entry("KP_3", "\"k3\"")
+            .entry("KP_4", "\"k4\"")
+            .entry("KP_5", "\"k5\"")
+            .entry("KP_6", "\"k6\"")
+            .entry("KP_7", "\"k7\"")
+            .entry("KP_8", "\"k8\"")
+            .entry("KP_9", "\"k9\"")
+            .entry("KP_Up", "\"kUp\"")
+            .entry("KP_Down", "\"kDown\"")
+            .entry("KP_Left", "\"kLeft\"")
+            .entry("KP_Right", "\"kRight\"")
+            .entry("KP_Home", "\"kHome\"")
+            .entry("KP_End", "\"kEnd\"")
+            .entry("KP_Begin", "\"kOrigin\"")
+            .entry("KP_Page_Up", "\"kPageUp\"")
+            .entry("KP_Page_Down", "\"kPageDown\"")
+            .entry("KP_Insert", "\"kInsert\"")
+            .entry("KP_Delete", "\"kDel\"")
             .build()
     )
     .unwrap();
@@ -63,9 +63,33 @@ command! -nargs=1 GuiLinespace call rpcnotify(1, 'Gui', 'Linespace', <q-args>)
 command! -nargs=1 GuiPopupmenu call rpcnotify(1, 'Gui', 'Option', 'Popupmenu', <args>)
 command! -nargs=1 GuiTabline call rpcnotify(1, 'Gui', 'Option', 'Tabline', <args>)
 command! -nargs=1 GuiCmdline call rpcnotify(1, 'Gui', 'Option', 'Cmdline', <args>)
+command! -nargs=1 GuiStatusStrip call rpcnotify(1, 'Gui', 'Option', 'StatusStrip', <args>)
+command! -nargs=1 GuiSmoothScroll call rpcnotify(1, 'Gui', 'Option', 'SmoothScroll', <args>)
+command! -nargs=1 GuiContextMenu call rpcnotify(1, 'Gui', 'Option', 'ContextMenu', <args>)
+command! -nargs=1 GuiKeycodeFallback call rpcnotify(1, 'Gui', 'Option', 'KeycodeFallback', <args>)
+command! -nargs=1 GuiMenubar call rpcnotify(1, 'Gui', 'Option', 'Menubar', <args>)
+command! -nargs=1 GuiToolbar call rpcnotify(1, 'Gui', 'Option', 'Toolbar', <args>)
 
 command! NGToggleSidebar call rpcnotify(1, 'Gui', 'Command', 'ToggleSidebar')
 command! NGShowProjectView call rpcnotify(1, 'Gui', 'Command', 'ShowProjectView')
+command! NGMenuRefresh call rpcnotify(1, 'Gui', 'Command', 'MenuRefresh')
 command! -nargs=+ NGTransparency call rpcnotify(1, 'Gui', 'Command', 'Transparency', <f-args>)
 command! -nargs=1 NGPreferDarkTheme call rpcnotify(1, 'Gui', 'Command', 'PreferDarkTheme', <q-args>)
 command! -nargs=1 NGSetCursorBlink call rpcnotify(1, 'Gui', 'Command', 'SetCursorBlink', <q-args>)
+command! -nargs=+ NGCursorAnimation call rpcnotify(1, 'Gui', 'Command', 'CursorAnimation', <f-args>)
+command! -nargs=+ NGFloatStyle call rpcnotify(1, 'Gui', 'Command', 'FloatStyle', <f-args>)
+
+" Neovim doesn't tell UIs when langmap is active, so track 'iminsert' for the lCursor highlight
+function! s:GuiLangmapUpdate() abort
+	let l:langmap = &iminsert == 1
+	if get(s:, 'gui_langmap', -1) != l:langmap
+		let s:gui_langmap = l:langmap
+		call rpcnotify(1, 'Gui', 'Option', 'Langmap', l:langmap)
+	endif
+endfunction
+
+augroup GuiLangmap
+	autocmd!
+	autocmd InsertEnter,InsertCharPre,CmdlineEnter * call s:GuiLangmapUpdate()
+	autocmd OptionSet iminsert call s:GuiLangmapUpdate()
+augroup END
@@ -34,13 +34,12 @@ pub struct Level {
 
 impl Level {
     pub fn insert(&mut self, c: String, shift: bool, render_state: &shell::RenderState) {
-        self.model_layout
-            .insert_char(c, shift, render_state.hl.default_hl());
+        self.model_layout.insert_char(c, shift, 0);
         self.update_preferred_size(render_state);
     }
 
     pub fn replace_from_ctx(&mut self, ctx: &CmdLineContext, render_state: &shell::RenderState) {
-        let content = ctx.get_lines(&render_state.hl);
+        let content = ctx.get_lines();
         self.replace_line(content.lines, false);
         self.prompt_offset = content.prompt_offset;
         self.model_layout
@@ -49,7 +48,7 @@ impl Level {
     }
 
     pub fn from_ctx(ctx: &CmdLineContext, render_state: &shell::RenderState) -> Self {
-        let content = ctx.get_lines(&render_state.hl);
+        let content = ctx.get_lines();
         let mut level = Level::from_lines(content.lines, ctx.max_width, render_state);
 
         level.prompt_offset = content.prompt_offset;
@@ -88,7 +87,7 @@ impl Level {
         max_width: i32,
         render_state: &shell::RenderState,
     ) -> Self {
-        let lines = content.to_attributed_content(&render_state.hl);
+        let lines = content.to_attributed_content();
         Level::from_lines(lines, max_width, render_state)
     }
 
@@ -129,21 +128,16 @@ impl Level {
     }
 }
 
-fn prompt_lines(
-    firstc: &str,
-    prompt: &str,
-    indent: u64,
-    hl: &HighlightMap,
-) -> (usize, Vec<HighlightedRange>) {
+fn prompt_lines(firstc: &str, prompt: &str, indent: u64) -> (usize, Vec<HighlightedRange>) {
     let prompt: Vec<HighlightedRange> = if !firstc.is_empty() {
         if firstc.len() >= indent as usize {
             vec![HighlightedRange {
-                highlight: hl.default_hl(),
+                hl_id: 0,
                 graphemes: vec![firstc.to_owned()],
             }]
         } else {
             vec![HighlightedRange::new(
-                hl.default_hl(),
+                0,
                 iter::once(firstc.to_owned())
                     .chain((firstc.len()..indent as usize).map(|_| " ".to_owned()))
                     .collect(),
@@ -153,7 +147,7 @@ fn prompt_lines(
         prompt
             .lines()
             .map(|l| HighlightedRange {
-                highlight: hl.default_hl(),
+                hl_id: 0,
                 graphemes: l.graphemes(true).map(|g| g.to_owned()).collect(),
             })
             .collect()
@@ -412,7 +406,7 @@ impl CmdLine {
         let mut state = self.state.borrow_mut();
         let render_state = state.render_state.clone();
         {
-            let attr_content = content.to_attributed_content(&render_state.borrow().hl);
+            let attr_content = content.to_attributed_content();
 
             let block = state.block.as_mut().unwrap();
             block.replace_line(attr_content, true);
@@ -526,10 +520,9 @@ pub struct CmdLineContext<'a> {
 }
 
 impl CmdLineContext<'_> {
-    fn get_lines(&self, hl: &HighlightMap) -> LineContent {
-        let mut content_line = self.content.to_attributed_content(hl);
-        let (prompt_offset, prompt_lines) =
-            prompt_lines(&self.firstc, &self.prompt, self.indent, hl);
+    fn get_lines(&self) -> LineContent {
+        let mut content_line = self.content.to_attributed_content();
+        let (prompt_offset, prompt_lines) = prompt_lines(&self.firstc, &self.prompt, self.indent);
 
         let mut content: Vec<_> = prompt_lines.into_iter().map(|line| vec![line]).collect();
 
@@ -552,17 +545,17 @@ struct LineContent {
 }
 
 trait ToAttributedModelContent {
-    fn to_attributed_content(&self, hl: &HighlightMap) -> Vec<HighlightedLine>;
+    fn to_attributed_content(&self) -> Vec<HighlightedLine>;
 }
 
 impl ToAttributedModelContent for Vec<Vec<(u64, String)>> {
-    fn to_attributed_content(&self, hl: &HighlightMap) -> Vec<HighlightedLine> {
+    fn to_attributed_content(&self) -> Vec<HighlightedLine> {
         self.iter()
             .map(|line_chars| {
                 line_chars
                     .iter()
                     .map(|c| HighlightedRange {
-                        highlight: hl.get(c.0.into()),
+                        hl_id: c.0,
                         graphemes: c.1.graphemes(true).map(|g| g.to_owned()).collect(),
                     })
                     .collect()
@@ -572,11 +565,11 @@ impl ToAttributedModelContent for Vec<Vec<(u64, String)>> {
 }
 
 impl ToAttributedModelContent for Vec<(u64, String)> {
-    fn to_attributed_content(&self, hl: &HighlightMap) -> Vec<HighlightedLine> {
+    fn to_attributed_content(&self) -> Vec<HighlightedLine> {
         vec![
             self.iter()
                 .map(|c| HighlightedRange {
-                    highlight: hl.get(c.0.into()),
+                    hl_id: c.0,
                     graphemes: c.1.graphemes(true).map(|g| g.to_owned()).collect(),
                 })
                 .collect(),
@@ -629,7 +622,7 @@ pub fn tree_button_press(
                 .collect()
         };
 
-        nvim.block_timeout(nvim.input(&apply_command)).report_err();
+        nvim.queue_input(apply_command);
     }
 }
 
@@ -161,6 +161,8 @@ impl WidgetImpl for CmdlineViewportObject {
                 &level.model_layout.model,
                 hl,
                 TransparencySettings::new(), // FIXME
+                (0.0, 0.0),
+                None,
             );
         }
 
@@ -6,9 +6,8 @@ use std::{
 };
 
 use crate::highlight::HighlightMap;
-use crate::mode;
+use crate::mode::{self, BlinkTimings};
 use crate::nvim::RedrawMode;
-use crate::render;
 use crate::render::CellMetrics;
 use crate::ui::UiMutex;
 use crate::ui_model::Cell;
@@ -57,12 +56,58 @@ impl BlinkCount {
     }
 }
 
+/// How the cursor blinks until Neovim sends the cursor modes of 'guicursor'
+const DEFAULT_BLINK: BlinkTimings = BlinkTimings {
+    wait: 500,
+    on: 500,
+    off: 300,
+};
+/// The cursor fades in and out over a few steps of this many milliseconds
+const BLINK_FADE_INTERVAL: u32 = 30;
+const BLINK_FADE_STEP: f64 = 0.34;
+const BLINK_FADE_DURATION: u32 = BLINK_FADE_INTERVAL * 3;
+
+/// How long the cursor takes to move to a new position, in microseconds
+const MOTION_DURATION: i64 = 120_000;
+/// How many fading copies of the cursor are drawn behind it while it moves
+const TRAIL_LENGTH: usize = 4;
+
+/// Settings for animating the cursor when it moves
+#[derive(Clone, Copy, Default)]
+pub struct CursorAnimation {
+    pub enabled: bool,
+    pub trail: bool,
+}
+
+/// A move of the cursor rectangle from one position to another
+struct Motion {
+    from: Rect,
+    to: Rect,
+    start: i64,
+}
+
+impl Motion {
+    fn progress(&self, now: i64) -> f64 {
+        ((now - self.start) as f64 / MOTION_DURATION as f64).clamp(0.0, 1.0)
+    }
+
+    fn rect_at(&self, progress: f64) -> Rect {
+        // Ease out, so the cursor lands softly
+        self.from
+            .interpolate(&self.to, 1.0 - (1.0 - progress).powi(3))
+    }
+}
+
 struct State<CB: CursorRedrawCb> {
     alpha: Alpha,
     anim_phase: AnimPhase,
     redraw_cb: Weak<UiMutex<CB>>,
 
+    target: Option<Rect>,
+    motion: Option<Motion>,
+
     timer: Option<glib::SourceId>,
+    blink: Option<BlinkTimings>,
     counter: Option<BlinkCount>,
     widget_focus: bool,
     toplevel_focus: bool,
@@ -74,7 +119,10 @@ impl<CB: CursorRedrawCb> State<CB> {
             alpha: Alpha(1.0),
             anim_phase: AnimPhase::Shown,
             redraw_cb,
+            target: None,
+            motion: None,
             timer: None,
+            blink: None,
             counter: None,
             widget_focus: false,
             toplevel_focus: false,
@@ -97,6 +145,8 @@ impl<CB: CursorRedrawCb> State<CB> {
 pub struct Cursor<CB: CursorRedrawCb> {
     state: Arc<UiMutex<State<CB>>>,
     mode_info: Option<mode::ModeInfo>,
+    langmap: bool,
+    animation: CursorAnimation,
 }
 
 impl<CB: CursorRedrawCb + 'static> Cursor<CB> {
@@ -104,11 +154,115 @@ impl<CB: CursorRedrawCb + 'static> Cursor<CB> {
         Cursor {
             state: Arc::new(UiMutex::new(State::new(redraw_cb))),
             mode_info: None,
+            langmap: false,
+            animation: CursorAnimation::default(),
+        }
+    }
+
+    pub fn set_animation(&mut self, animation: CursorAnimation) {
+        self.animation = animation;
+        if !animation.enabled {
+            self.state.borrow_mut().motion = None;
         }
     }
 
+    /// Update the position the cursor should end up at, and return where it should be drawn at
+    /// frame time `now`. `now` is `None` when animations are disabled.
+    pub fn move_to(&self, target: Rect, now: Option<i64>) -> Rect {
+        let mut state = self.state.borrow_mut();
+        let now = match now {
+            Some(now) if self.animation.enabled => now,
+            _ => {
+                state.target = Some(target);
+                state.motion = None;
+                return target;
+            }
+        };
+
+        if state.target != Some(target) {
+            let from = match (&state.motion, state.target) {
+                (Some(motion), _) => motion.rect_at(motion.progress(now)),
+                (None, Some(from)) => from,
+                (None, None) => target,
+            };
+            state.target = Some(target);
+            state.motion = Some(Motion {
+                from,
+                to: target,
+                start: now,
+            });
+        }
+
+        match &state.motion {
+            Some(motion) if motion.progress(now) < 1.0 => motion.rect_at(motion.progress(now)),
+            _ => {
+                state.motion = None;
+                target
+            }
+        }
+    }
+
+    /// The positions and opacities of the trail left behind by a moving cursor, oldest first
+    pub fn trail(&self, now: i64) -> Vec<(Rect, f64)> {
+        let state = self.state.borrow();
+        let Some(motion) = state.motion.as_ref().filter(|_| self.animation.trail) else {
+            return Vec::new();
+        };
+
+        let progress = motion.progress(now);
+        (1..=TRAIL_LENGTH)
+            .rev()
+            .map(|step| {
+                let step = step as f64 / (TRAIL_LENGTH + 1) as f64;
+                (
+                    motion.rect_at((progress - step * 0.5).max(0.0)),
+                    (1.0 - step) * 0.5,
+                )
+            })
+            .collect()
+    }
+
+    /// Whether the cursor is still moving towards its target and needs more frames
+    pub fn is_moving(&self) -> bool {
+        self.state.borrow().motion.is_some()
+    }
+
     pub fn set_mode_info(&mut self, mode_info: Option<mode::ModeInfo>) {
+        let blink_changed = self.blink() != Self::blink_of(mode_info.as_ref());
         self.mode_info = mode_info;
+
+        // Restart blinking with the timings of the new mode
+        if blink_changed
+            && !matches!(
+                self.state.borrow().anim_phase,
+                AnimPhase::Busy | AnimPhase::NoFocus
+            )
+        {
+            self.start();
+        }
+    }
+
+    /// Set whether langmap ('iminsert') is active, which picks the lCursor highlight
+    pub fn set_langmap(&mut self, langmap: bool) {
+        self.langmap = langmap;
+    }
+
+    /// The highlight id the cursor is drawn with, if 'guicursor' sets one
+    pub fn attr_id(&self) -> Option<u64> {
+        self.mode_info
+            .as_ref()
+            .and_then(|mode_info| mode_info.attr_id(self.langmap))
+    }
+
+    fn blink_of(mode_info: Option<&mode::ModeInfo>) -> Option<BlinkTimings> {
+        match mode_info {
+            Some(mode_info) => mode_info.blink(),
+            None => Some(DEFAULT_BLINK),
+        }
+    }
+
+    fn blink(&self) -> Option<BlinkTimings> {
+        Self::blink_of(self.mode_info.as_ref())
     }
 
     pub fn set_cursor_blink(&mut self, val: i32) {
@@ -121,25 +275,24 @@ impl<CB: CursorRedrawCb + 'static> Cursor<CB> {
     }
 
     pub fn start(&mut self) {
-        let blinkwait = self
-            .mode_info
-            .as_ref()
-            .and_then(|mi| mi.blinkwait)
-            .unwrap_or(500);
+        let blink = self.blink();
 
         let state = self.state.clone();
         let mut mut_state = self.state.borrow_mut();
 
         mut_state.reset_to(AnimPhase::Shown);
+        mut_state.blink = blink;
 
         if let Some(counter) = &mut mut_state.counter {
             counter.count = 0;
         }
 
-        mut_state.timer = Some(glib::timeout_add(
-            Duration::from_millis(if blinkwait > 0 { blinkwait as u64 } else { 500 }),
-            move || anim_step(&state),
-        ));
+        if let Some(blink) = blink {
+            mut_state.timer = Some(glib::timeout_add(
+                Duration::from_millis(blink.wait.into()),
+                move || anim_step(&state),
+            ));
+        }
     }
 
     pub fn reset_state(&mut self) {
@@ -201,24 +354,21 @@ impl<CB: CursorRedrawCb + 'static> Cursor<CB> {
     pub fn snapshot(
         &self,
         snapshot: &gtk::Snapshot,
-        font_ctx: &render::Context,
-        (x, y): (f64, f64),
+        rect: &Rect,
         cell: &Cell,
-        double_width: bool,
         hl: &HighlightMap,
         fade_percentage: f64,
         filled_alpha: f64,
     ) -> bool {
         let state = self.state.borrow();
 
-        let cell_metrics = font_ctx.cell_metrics();
-        let (y, w, h) = cursor_rect(self.mode_info(), cell_metrics, y, double_width);
-        let (x, y, w, h) = (x as f32, y as f32, w as f32, h as f32);
+        let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+        let (cursor_bg, _) = hl.cursor_colors(self.attr_id(), cell);
 
         if state.anim_phase == AnimPhase::NoFocus {
             #[rustfmt::skip]
             {
-                let bg = hl.cursor_bg().to_rgbo(filled_alpha);
+                let bg = cursor_bg.to_rgbo(filled_alpha);
                 snapshot.append_color(&bg, &Rect::new(          x,           y,   w, 1.0));
                 snapshot.append_color(&bg, &Rect::new(          x,           y, 1.0,   h));
                 snapshot.append_color(&bg, &Rect::new(          x, y + h - 1.0,   w, 1.0));
@@ -228,9 +378,9 @@ impl<CB: CursorRedrawCb + 'static> Cursor<CB> {
         } else {
             let bg = hl
                 .actual_cell_bg(cell)
-                .fade(&hl.cursor_bg(), fade_percentage)
+                .fade(&cursor_bg, fade_percentage)
                 .to_rgbo(filled_alpha);
-            snapshot.append_color(&bg, &Rect::new(x, y, w, h));
+            snapshot.append_color(&bg, rect);
             true
         }
     }
@@ -319,26 +469,33 @@ pub fn cursor_rect(
 fn anim_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> glib::ControlFlow {
     let mut mut_state = state.borrow_mut();
 
+    let Some(blink) = mut_state.blink else {
+        mut_state.timer = None;
+        return glib::ControlFlow::Break;
+    };
+
+    // The fades count towards the on and off times, so a whole blink takes exactly as long as
+    // 'guicursor' says
     let next_event = match mut_state.anim_phase {
         AnimPhase::Shown => {
             if let Some(counter) = &mut mut_state.counter {
                 if counter.count < counter.max {
                     counter.count += 1;
                     mut_state.anim_phase = AnimPhase::Hide;
-                    Some(60)
+                    Some(BLINK_FADE_INTERVAL)
                 } else {
                     None
                 }
             } else {
                 mut_state.anim_phase = AnimPhase::Hide;
-                Some(60)
+                Some(BLINK_FADE_INTERVAL)
             }
         }
         AnimPhase::Hide => {
-            if !mut_state.alpha.hide(0.3) {
+            if !mut_state.alpha.hide(BLINK_FADE_STEP) {
                 mut_state.anim_phase = AnimPhase::Hidden;
 
-                Some(300)
+                Some(blink.off.saturating_sub(BLINK_FADE_DURATION))
             } else {
                 None
             }
@@ -346,13 +503,13 @@ fn anim_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> g
         AnimPhase::Hidden => {
             mut_state.anim_phase = AnimPhase::Show;
 
-            Some(60)
+            Some(BLINK_FADE_INTERVAL)
         }
         AnimPhase::Show => {
-            if !mut_state.alpha.show(0.3) {
+            if !mut_state.alpha.show(BLINK_FADE_STEP) {
                 mut_state.anim_phase = AnimPhase::Shown;
 
-                Some(500)
+                Some(blink.on.saturating_sub(BLINK_FADE_DURATION))
             } else {
                 None
             }
@@ -368,7 +525,7 @@ fn anim_step<CB: CursorRedrawCb + 'static>(state: &Arc<UiMutex<State<CB>>>) -> g
     if let Some(timeout) = next_event {
         let moved_state = state.clone();
         mut_state.timer = Some(glib::timeout_add(
-            Duration::from_millis(timeout),
+            Duration::from_millis(timeout.into()),
             move || anim_step(&moved_state),
         ));
 
@@ -460,4 +617,19 @@ mod tests {
         assert_eq!(char_width / 4.0, width);
         assert_eq!(line_height, height);
     }
+
+    #[test]
+    fn test_motion_progress() {
+        let motion = Motion {
+            from: Rect::new(0.0, 0.0, 10.0, 20.0),
+            to: Rect::new(100.0, 40.0, 10.0, 20.0),
+            start: 1000,
+        };
+
+        assert_eq!(0.0, motion.progress(0));
+        assert_eq!(0.5, motion.progress(1000 + MOTION_DURATION / 2));
+        assert_eq!(1.0, motion.progress(1000 + MOTION_DURATION * 2));
+        assert_eq!(motion.from, motion.rect_at(0.0));
+        assert_eq!(motion.to, motion.rect_at(1.0));
+    }
 }
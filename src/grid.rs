@@ -1,17 +1,24 @@
 use std::ops::{Index, IndexMut};
-use std::rc::Rc;
 
 use fnv::FnvHashMap;
 
-use nvim_rs::Value;
-
-use crate::highlight::{Highlight, HighlightMap};
+use crate::nvim::GridLineCell;
 use crate::ui_model::{ModelRect, UiModel};
 
-const DEFAULT_GRID: u64 = 1;
+pub const DEFAULT_GRID: u64 = 1;
+
+/// The zindex Neovim uses for the message grid when ext_messages isn't enabled
+const MSG_GRID_ZINDEX: u64 = 200;
+
+/// How deep a chain of floats anchored to other floats may get before we give up resolving it
+const MAX_ANCHOR_DEPTH: usize = 16;
 
 pub struct GridMap {
     grids: FnvHashMap<u64, Grid>,
+    /// The grid which most recently received a grid_cursor_goto event
+    current_grid: u64,
+    /// Counter used for stacking grids that share the same zindex in the order they were shown
+    next_stack_idx: u64,
 }
 
 impl Index<u64> for GridMap {
@@ -32,19 +39,35 @@ impl GridMap {
     pub fn new() -> Self {
         GridMap {
             grids: FnvHashMap::default(),
+            current_grid: DEFAULT_GRID,
+            next_stack_idx: 0,
+        }
+    }
+
+    /// The id of the grid that currently holds the cursor
+    pub fn current_id(&self) -> u64 {
+        if self.grids.contains_key(&self.current_grid) {
+            self.current_grid
+        } else {
+            DEFAULT_GRID
         }
     }
 
     pub fn current(&self) -> Option<&Grid> {
-        self.grids.get(&DEFAULT_GRID)
+        self.grids.get(&self.current_id())
     }
 
     pub fn current_model_mut(&mut self) -> Option<&mut UiModel> {
-        self.grids.get_mut(&DEFAULT_GRID).map(|g| &mut g.model)
+        let idx = self.current_id();
+        self.grids.get_mut(&idx).map(|g| &mut g.model)
     }
 
     pub fn current_model(&self) -> Option<&UiModel> {
-        self.grids.get(&DEFAULT_GRID).map(|g| &g.model)
+        self.current().map(|g| &g.model)
+    }
+
+    pub fn get(&self, idx: u64) -> Option<&Grid> {
+        self.grids.get(&idx)
     }
 
     pub fn get_or_create(&mut self, idx: u64) -> &mut Grid {
@@ -55,6 +78,157 @@ impl GridMap {
         self.grids.remove(&idx);
     }
 
+    pub fn set_current(&mut self, idx: u64) {
+        self.current_grid = idx;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &Grid)> {
+        self.grids.iter().map(|(idx, grid)| (*idx, grid))
+    }
+
+    pub fn models_mut(&mut self) -> impl Iterator<Item = &mut UiModel> {
+        self.grids.values_mut().map(|g| &mut g.model)
+    }
+
+    /// Place a grid at the given position, in cells relative to the default grid. Grids placed
+    /// later are stacked above grids with the same zindex that were placed before them.
+    pub fn set_position(&mut self, idx: u64, row: f64, col: f64, zindex: u64) {
+        let grid = self.grids.entry(idx).or_insert_with(Grid::new);
+
+        // Only restack the grid if it wasn't already visible at this zindex, otherwise every
+        // win_pos event would bring the window to the front
+        let stack_idx = match grid.position {
+            Some(ref pos) if !grid.hidden && pos.zindex == zindex => pos.stack_idx,
+            _ => {
+                self.next_stack_idx += 1;
+                self.next_stack_idx
+            }
+        };
+
+        grid.position = Some(GridPosition {
+            row,
+            col,
+            zindex,
+            stack_idx,
+            float: None,
+        });
+        grid.hidden = false;
+    }
+
+    pub fn set_window_position(&mut self, idx: u64, row: u64, col: u64) {
+        self.set_position(idx, row as f64, col as f64, 0);
+    }
+
+    pub fn set_float_position(
+        &mut self,
+        idx: u64,
+        anchor: FloatAnchor,
+        anchor_grid: u64,
+        row: f64,
+        col: f64,
+        zindex: u64,
+    ) {
+        self.set_position(idx, row, col, zindex);
+        if let Some(pos) = self.grids.get_mut(&idx).and_then(|g| g.position.as_mut()) {
+            pos.float = Some(FloatPlacement {
+                anchor,
+                anchor_grid,
+            });
+        }
+    }
+
+    pub fn set_msg_position(&mut self, idx: u64, row: u64, zindex: Option<u64>) {
+        self.set_position(idx, row as f64, 0.0, zindex.unwrap_or(MSG_GRID_ZINDEX));
+    }
+
+    pub fn hide(&mut self, idx: u64) {
+        if let Some(grid) = self.grids.get_mut(&idx) {
+            grid.hidden = true;
+        }
+    }
+
+    pub fn close(&mut self, idx: u64) {
+        if let Some(grid) = self.grids.get_mut(&idx) {
+            grid.hidden = true;
+            grid.position = None;
+            grid.viewport = None;
+        }
+    }
+
+    /// Get the ids of all grids that should be drawn, ordered from bottom to top
+    pub fn visible_grids(&self) -> Vec<u64> {
+        let mut visible: Vec<_> = self
+            .grids
+            .iter()
+            .filter_map(|(idx, grid)| grid.stacking_order(*idx).map(|order| (order, *idx)))
+            .collect();
+        visible.sort_unstable();
+        visible.into_iter().map(|(_, idx)| idx).collect()
+    }
+
+    /// Find the topmost visible grid at the given position (in fractional cells relative to the
+    /// default grid), and return its id along with the position translated to be relative to that
+    /// grid
+    pub fn grid_at(&self, (row, col): (f64, f64)) -> Option<(u64, (f64, f64))> {
+        self.visible_grids().into_iter().rev().find_map(|idx| {
+            let grid = &self.grids[&idx];
+            let (grid_row, grid_col) = self.origin(idx);
+            let (row, col) = (row - grid_row, col - grid_col);
+
+            if row >= 0.0
+                && col >= 0.0
+                && row < grid.model.rows as f64
+                && col < grid.model.columns as f64
+            {
+                Some((idx, (row, col)))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The position of the top left corner of a grid in (row, col) cells relative to the default
+    /// grid. Floating grids are resolved against the current position of their anchor grid, which
+    /// may be another float, and kept within the bounds of the default grid like Neovim does.
+    pub fn origin(&self, idx: u64) -> (f64, f64) {
+        self.resolve_origin(idx, 0)
+    }
+
+    fn resolve_origin(&self, idx: u64, depth: usize) -> (f64, f64) {
+        let Some(grid) = self.grids.get(&idx) else {
+            return (0.0, 0.0);
+        };
+        let Some(ref pos) = grid.position else {
+            return (0.0, 0.0);
+        };
+        let Some(ref float) = pos.float else {
+            return (pos.row, pos.col);
+        };
+
+        let (anchor_row, anchor_col) = if float.anchor_grid == idx || depth >= MAX_ANCHOR_DEPTH {
+            (0.0, 0.0)
+        } else {
+            self.resolve_origin(float.anchor_grid, depth + 1)
+        };
+
+        let (rows, columns) = (grid.model.rows as f64, grid.model.columns as f64);
+        let mut row = anchor_row + pos.row;
+        let mut col = anchor_col + pos.col;
+        if matches!(float.anchor, FloatAnchor::SW | FloatAnchor::SE) {
+            row -= rows;
+        }
+        if matches!(float.anchor, FloatAnchor::NE | FloatAnchor::SE) {
+            col -= columns;
+        }
+
+        if let Some(default_grid) = self.grids.get(&DEFAULT_GRID) {
+            row = row.min(default_grid.model.rows as f64 - rows);
+            col = col.min(default_grid.model.columns as f64 - columns);
+        }
+
+        (row.max(0.0), col.max(0.0))
+    }
+
     pub fn clear_glyphs(&mut self) {
         for grid in self.grids.values_mut() {
             grid.model.clear_glyphs();
@@ -70,14 +244,107 @@ impl GridMap {
     }
 }
 
+/// Where a grid is placed on the screen, as reported by Neovim with ext_multigrid
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridPosition {
+    /// Position of the top left corner of the grid, in cells relative to the default grid
+    pub row: f64,
+    pub col: f64,
+    pub zindex: u64,
+    stack_idx: u64,
+    /// Set if this is a floating window, in which case row and col are relative to the anchor
+    float: Option<FloatPlacement>,
+}
+
+/// Which corner of a floating window is placed at its anchor position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatAnchor {
+    NW,
+    NE,
+    SW,
+    SE,
+}
+
+impl FloatAnchor {
+    pub fn new(anchor: &str) -> Result<Self, String> {
+        match anchor {
+            "NW" => Ok(FloatAnchor::NW),
+            "NE" => Ok(FloatAnchor::NE),
+            "SW" => Ok(FloatAnchor::SW),
+            "SE" => Ok(FloatAnchor::SE),
+            _ => Err(format!("Unknown float anchor {anchor}")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct FloatPlacement {
+    anchor: FloatAnchor,
+    anchor_grid: u64,
+}
+
+/// The part of the buffer currently visible in a window's grid, from win_viewport
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GridViewport {
+    pub topline: i64,
+    pub botline: i64,
+    pub curline: i64,
+    pub curcol: i64,
+    pub line_count: i64,
+    pub scroll_delta: i64,
+}
+
 pub struct Grid {
     model: UiModel,
+    position: Option<GridPosition>,
+    hidden: bool,
+    viewport: Option<GridViewport>,
 }
 
 impl Grid {
     pub fn new() -> Self {
         Grid {
             model: UiModel::default(),
+            position: None,
+            hidden: false,
+            viewport: None,
+        }
+    }
+
+    #[inline]
+    pub fn model(&self) -> &UiModel {
+        &self.model
+    }
+
+    pub fn position(&self) -> Option<&GridPosition> {
+        self.position.as_ref()
+    }
+
+    /// Whether this grid is a floating window placed with win_float_pos
+    pub fn is_float(&self) -> bool {
+        self.position
+            .as_ref()
+            .is_some_and(|pos| pos.float.is_some())
+    }
+
+    pub fn viewport(&self) -> Option<&GridViewport> {
+        self.viewport.as_ref()
+    }
+
+    pub fn set_viewport(&mut self, viewport: GridViewport) {
+        self.viewport = Some(viewport);
+    }
+
+    /// The key this grid should be sorted by when stacking grids, or None if it isn't visible.
+    /// The default grid is always at the bottom.
+    fn stacking_order(&self, idx: u64) -> Option<(u64, u64)> {
+        if idx == DEFAULT_GRID {
+            return Some((0, 0));
+        }
+
+        match self.position {
+            Some(ref pos) if !self.hidden => Some((pos.zindex, pos.stack_idx)),
+            _ => None,
         }
     }
 
@@ -101,57 +368,35 @@ impl Grid {
         self.model.set_cursor(row, col)
     }
 
-    pub fn clear(&mut self, default_hl: &Rc<Highlight>) {
-        self.model.clear(default_hl);
+    pub fn clear(&mut self) {
+        self.model.clear();
     }
 
-    #[allow(clippy::get_first)] // get(0), get(1), get(2) more consistent than .first(), .get(1)
-    pub fn line(
-        &mut self,
-        row: usize,
-        col_start: usize,
-        cells: Vec<Vec<Value>>,
-        highlights: &HighlightMap,
-    ) -> ModelRect {
-        let mut hl_id = None;
+    pub fn line(&mut self, row: usize, col_start: usize, cells: Vec<GridLineCell>) -> ModelRect {
         let mut col_end = col_start;
 
         for cell in cells {
-            let ch = cell.get(0).unwrap().as_str().unwrap_or("");
-            hl_id = cell.get(1).and_then(|h| h.as_u64()).or(hl_id);
-            let repeat = cell.get(2).and_then(|r| r.as_u64()).unwrap_or(1) as usize;
-
             self.model.put(
                 row,
                 col_end,
-                ch,
-                ch.is_empty(),
-                repeat,
-                highlights.get(hl_id),
+                &cell.text,
+                cell.text.is_empty(),
+                cell.repeat,
+                cell.hl_id.unwrap_or(0),
             );
-            col_end += repeat;
+            col_end += cell.repeat;
         }
 
         ModelRect::new(row, row, col_start, col_end - 1)
     }
 
-    pub fn scroll(
-        &mut self,
-        top: u64,
-        bot: u64,
-        left: u64,
-        right: u64,
-        rows: i64,
-        _: i64,
-        default_hl: &Rc<Highlight>,
-    ) {
+    pub fn scroll(&mut self, top: u64, bot: u64, left: u64, right: u64, rows: i64, _: i64) {
         self.model.scroll(
             top as i64,
             bot as i64 - 1,
             left as usize,
             right as usize - 1,
             rows,
-            default_hl,
         )
     }
 }
@@ -168,4 +413,86 @@ mod tests {
 
         assert_eq!((3, 4), grids.get_or_create(7).get_cursor());
     }
+
+    #[test]
+    fn test_visible_grids_are_stacked() {
+        let mut grids = GridMap::new();
+
+        grids.get_or_create(DEFAULT_GRID);
+        grids.get_or_create(2);
+        grids.get_or_create(3);
+        grids.get_or_create(4);
+        assert_eq!(vec![DEFAULT_GRID], grids.visible_grids());
+
+        grids.set_msg_position(4, 10, None);
+        grids.set_window_position(3, 0, 0);
+        grids.set_window_position(2, 0, 40);
+        assert_eq!(vec![DEFAULT_GRID, 3, 2, 4], grids.visible_grids());
+
+        // Repositioning a visible window doesn't restack it
+        grids.set_window_position(3, 1, 0);
+        assert_eq!(vec![DEFAULT_GRID, 3, 2, 4], grids.visible_grids());
+
+        grids.hide(2);
+        assert_eq!(vec![DEFAULT_GRID, 3, 4], grids.visible_grids());
+
+        grids.close(3);
+        assert_eq!(vec![DEFAULT_GRID, 4], grids.visible_grids());
+    }
+
+    #[test]
+    fn test_grid_at() {
+        let mut grids = GridMap::new();
+
+        grids.get_or_create(DEFAULT_GRID).resize(80, 24);
+        grids.get_or_create(2).resize(40, 10);
+        grids.set_window_position(2, 5, 40);
+
+        assert_eq!(Some((DEFAULT_GRID, (1.0, 1.0))), grids.grid_at((1.0, 1.0)));
+        assert_eq!(Some((2, (0.5, 2.0))), grids.grid_at((5.5, 42.0)));
+        assert_eq!(
+            Some((DEFAULT_GRID, (15.0, 42.0))),
+            grids.grid_at((15.0, 42.0))
+        );
+        assert_eq!(None, grids.grid_at((30.0, 0.0)));
+    }
+
+    #[test]
+    fn test_float_origin() {
+        let mut grids = GridMap::new();
+
+        grids.get_or_create(DEFAULT_GRID).resize(80, 24);
+        grids.get_or_create(2).resize(40, 10);
+        grids.get_or_create(3).resize(10, 4);
+        grids.get_or_create(4).resize(20, 5);
+        grids.set_window_position(2, 2, 10);
+
+        grids.set_float_position(3, FloatAnchor::NW, 2, 1.0, 3.0, 50);
+        assert_eq!((3.0, 13.0), grids.origin(3));
+        assert!(grids.get(3).unwrap().is_float());
+
+        // Floats can be anchored to other floats, and follow them when they move
+        grids.set_float_position(4, FloatAnchor::SE, 3, 0.0, 0.0, 60);
+        assert_eq!((0.0, 0.0), grids.origin(4));
+        grids.set_float_position(3, FloatAnchor::NW, 2, 10.0, 30.0, 50);
+        assert_eq!((7.0, 20.0), grids.origin(4));
+        assert_eq!(vec![DEFAULT_GRID, 2, 3, 4], grids.visible_grids());
+
+        // Floats are kept on the screen
+        grids.set_float_position(3, FloatAnchor::NW, DEFAULT_GRID, 22.0, 78.0, 50);
+        assert_eq!((20.0, 70.0), grids.origin(3));
+    }
+
+    #[test]
+    fn test_current_falls_back_to_default_grid() {
+        let mut grids = GridMap::new();
+
+        grids.get_or_create(DEFAULT_GRID);
+        grids.get_or_create(2);
+        grids.set_current(2);
+        assert_eq!(2, grids.current_id());
+
+        grids.destroy(2);
+        assert_eq!(DEFAULT_GRID, grids.current_id());
+    }
 }
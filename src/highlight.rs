@@ -23,6 +23,8 @@ pub struct HighlightMap {
     pmenu: Rc<Highlight>,
     pmenu_sel: Rc<Highlight>,
     cursor: Rc<Highlight>,
+    /// The 'pumblend' option, from 0 (opaque) to 100 (fully transparent)
+    pumblend: u8,
 }
 
 /// Enum for the 'background' setting in neovim, which we track to determine default colors
@@ -32,6 +34,27 @@ pub enum BackgroundState {
     Dark,
 }
 
+/// Window separators and status lines, which can be dragged to resize windows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separator {
+    /// Between windows side by side
+    Vertical,
+    /// The status line below a window
+    Horizontal,
+}
+
+impl Separator {
+    fn from_ui_name(ui_name: &str) -> Option<Self> {
+        match ui_name {
+            "WinSeparator" | "VertSplit" => Some(Separator::Vertical),
+            "StatusLine" | "StatusLineNC" | "StatusLineTerm" | "StatusLineTermNC" => {
+                Some(Separator::Horizontal)
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Default)]
 pub struct HighlightUpdates {
     pub pmenu: bool,
@@ -55,15 +78,12 @@ impl HighlightMap {
             pmenu: default_hl.clone(),
             pmenu_sel: default_hl.clone(),
             cursor: default_hl.clone(),
+            pumblend: 0,
 
             default_hl,
         }
     }
 
-    pub fn default_hl(&self) -> Rc<Highlight> {
-        self.default_hl.clone()
-    }
-
     pub fn set_defaults(
         &mut self,
         fg: Option<Color>,
@@ -135,7 +155,13 @@ impl HighlightMap {
         hl: &HashMap<String, Value>,
         info: &[HashMap<String, Value>],
     ) -> HighlightUpdates {
-        let hl = Rc::new(Highlight::from_value_map(hl));
+        let mut hl = Highlight::from_value_map(hl);
+        hl.separator = info.iter().find_map(|item| {
+            item.get("ui_name")
+                .and_then(Value::as_str)
+                .and_then(Separator::from_ui_name)
+        });
+        let hl = Rc::new(hl);
         let mut updates = HighlightUpdates::default();
 
         for item in info {
@@ -160,41 +186,56 @@ impl HighlightMap {
         updates
     }
 
-    pub fn cell_fg<'a>(&'a self, cell: &'a Cell) -> Option<&'a Color> {
-        if cell.hl.reverse {
-            cell.hl.background.as_ref().or_else(|| Some(self.bg()))
+    /// The highlight of a cell, falling back to the default one for ids Neovim hasn't defined
+    pub fn cell_hl(&self, cell: &Cell) -> &Highlight {
+        self.highlights
+            .get(&cell.hl_id)
+            .or_else(|| self.highlights.get(&0))
+            .unwrap_or(&self.default_hl)
+    }
+
+    pub fn cell_fg(&self, cell: &Cell) -> Option<&Color> {
+        let hl = self.cell_hl(cell);
+        if hl.reverse {
+            hl.background.as_ref().or_else(|| Some(self.bg()))
         } else {
-            cell.hl.foreground.as_ref()
+            hl.foreground.as_ref()
         }
     }
 
-    pub fn actual_cell_fg<'a>(&'a self, cell: &'a Cell) -> &'a Color {
-        if cell.hl.reverse {
-            cell.hl.background.as_ref().unwrap_or_else(|| self.bg())
+    pub fn actual_cell_fg(&self, cell: &Cell) -> &Color {
+        let hl = self.cell_hl(cell);
+        if hl.reverse {
+            hl.background.as_ref().unwrap_or_else(|| self.bg())
         } else {
-            cell.hl.foreground.as_ref().unwrap_or_else(|| self.fg())
+            hl.foreground.as_ref().unwrap_or_else(|| self.fg())
         }
     }
 
-    pub fn cell_bg<'a>(&'a self, cell: &'a Cell) -> Option<&'a Color> {
-        if cell.hl.reverse {
-            cell.hl.foreground.as_ref().or_else(|| Some(self.fg()))
+    pub fn cell_bg(&self, cell: &Cell) -> Option<&Color> {
+        let hl = self.cell_hl(cell);
+        if hl.reverse {
+            hl.foreground.as_ref().or_else(|| Some(self.fg()))
         } else {
-            cell.hl.background.as_ref()
+            hl.background.as_ref()
         }
     }
 
-    pub fn actual_cell_bg<'a>(&'a self, cell: &'a Cell) -> &'a Color {
-        if cell.hl.reverse {
-            cell.hl.foreground.as_ref().unwrap_or_else(|| self.fg())
+    pub fn actual_cell_bg(&self, cell: &Cell) -> &Color {
+        let hl = self.cell_hl(cell);
+        if hl.reverse {
+            hl.foreground.as_ref().unwrap_or_else(|| self.fg())
         } else {
-            cell.hl.background.as_ref().unwrap_or_else(|| self.bg())
+            hl.background.as_ref().unwrap_or_else(|| self.bg())
         }
     }
 
     #[inline]
-    pub fn cell_sp<'a>(&'a self, cell: &'a Cell) -> Option<&'a Color> {
-        cell.hl.special.as_ref().or(self.sp_color.as_ref())
+    pub fn cell_sp(&self, cell: &Cell) -> Option<&Color> {
+        self.cell_hl(cell)
+            .special
+            .as_ref()
+            .or(self.sp_color.as_ref())
     }
 
     pub fn pmenu_bg(&self) -> &Color {
@@ -205,6 +246,16 @@ impl HighlightMap {
         }
     }
 
+    pub fn set_pumblend(&mut self, pumblend: u64) {
+        self.pumblend = pumblend.min(100) as u8;
+    }
+
+    /// The alpha value to draw the popup menu background with, using the blend of the Pmenu
+    /// highlight if it's more transparent than 'pumblend'
+    pub fn pmenu_bg_alpha(&self) -> f64 {
+        1.0 - f64::from(self.pmenu.blend.max(self.pumblend)) / 100.0
+    }
+
     pub fn pmenu_fg(&self) -> &Color {
         if self.pmenu.reverse {
             self.pmenu.background.as_ref().unwrap_or_else(|| self.bg())
@@ -248,6 +299,35 @@ impl HighlightMap {
             self.cursor.background.unwrap_or_else(|| self.bg().invert())
         }
     }
+
+    pub fn cursor_fg(&self) -> Color {
+        if self.cursor.reverse {
+            self.cursor.background.unwrap_or_else(|| *self.bg())
+        } else {
+            self.cursor.foreground.unwrap_or_else(|| *self.bg())
+        }
+    }
+
+    /// The background and foreground colors of the cursor over `cell`, using the highlight
+    /// `attr_id` from 'guicursor'. Id 0 reverses the colors of the cell, and without an id the
+    /// Cursor group is used.
+    pub fn cursor_colors(&self, attr_id: Option<u64>, cell: &Cell) -> (Color, Color) {
+        let hl = match attr_id {
+            None => return (self.cursor_bg(), self.cursor_fg()),
+            Some(0) => None,
+            Some(idx) => self.highlights.get(&idx),
+        };
+        let (fg, bg) = match hl {
+            Some(hl) if hl.reverse => (hl.background, hl.foreground),
+            Some(hl) => (hl.foreground, hl.background),
+            None => (None, None),
+        };
+
+        (
+            bg.unwrap_or_else(|| *self.actual_cell_fg(cell)),
+            fg.unwrap_or_else(|| *self.actual_cell_bg(cell)),
+        )
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -257,12 +337,19 @@ pub struct Highlight {
     pub underline: bool,
     pub underdouble: bool, // underline should always be true if this is true
     pub underdotted: bool,
+    pub undercurl: bool,
     pub underdashed: bool,
     pub strikethrough: bool,
     pub foreground: Option<Color>,
     pub background: Option<Color>,
     pub special: Option<Color>,
     pub reverse: bool,
+    /// How transparent the background is, from 0 (opaque) to 100 (fully transparent)
+    pub blend: u8,
+    /// The target of a hyperlink, set by Neovim 0.10+
+    pub url: Option<String>,
+    /// Set if this highlights a window separator, with ext_hlstate
+    pub separator: Option<Separator>,
 }
 
 impl Highlight {
@@ -276,9 +363,13 @@ impl Highlight {
             underline: false,
             underdouble: false,
             underdotted: false,
+            undercurl: false,
             underdashed: false,
             strikethrough: false,
             reverse: false,
+            blend: 0,
+            url: None,
+            separator: None,
         }
     }
 
@@ -314,15 +405,18 @@ impl Highlight {
                     model_attrs.underline = true;
                     model_attrs.underdouble = true;
                 }
-                // TODO: Add support for real undercurls and don't just draw them as underdots (#42)
-                "underdotted" | "undercurl" => model_attrs.underdotted = true,
+                "underdotted" => model_attrs.underdotted = true,
+                "undercurl" => model_attrs.undercurl = true,
                 "underdashed" => model_attrs.underdashed = true,
                 "strikethrough" => model_attrs.strikethrough = true,
-                "blend" => (),
+                "blend" => {
+                    if let Some(blend) = val.as_u64() {
+                        model_attrs.blend = blend.min(100) as u8;
+                    }
+                }
                 // TODO: These two are not documented anywhere but used by the fzf plugin
                 "fg_indexed" | "bg_indexed" => (),
-                // TODO: This is used in Neovim 0.10 to indicate the target of a link.
-                "url" => (),
+                "url" => model_attrs.url = val.as_str().map(str::to_owned),
                 // TODO: This is used in Neovim 0.10 to indicate that attributes should be
                 // overridden instead of combined.
                 "nocombine" => (),
@@ -1,11 +1,27 @@
 use std::env;
 
+use gdk::prelude::*;
 use log::debug;
 
-use crate::nvim::{ErrorReport, NvimSession};
+use crate::nvim::NvimSession;
 
 include!(concat!(env!("OUT_DIR"), "/key_map_table.rs"));
 
+/// Modifiers that turn a key into a shortcut, rather than text
+const SHORTCUT_MODIFIERS: gdk::ModifierType = gdk::ModifierType::CONTROL_MASK
+    .union(gdk::ModifierType::ALT_MASK)
+    .union(gdk::ModifierType::META_MASK)
+    .union(gdk::ModifierType::SUPER_MASK)
+    .union(gdk::ModifierType::HYPER_MASK);
+
+/// Modifiers Neovim knows as `D`, the Super key. Some keymaps report Alt as both Alt and Meta,
+/// so Meta only counts as `D` on its own, like the Command key on macOS.
+fn has_super(state: gdk::ModifierType) -> bool {
+    state.intersects(gdk::ModifierType::SUPER_MASK | gdk::ModifierType::HYPER_MASK)
+        || (state.contains(gdk::ModifierType::META_MASK)
+            && !state.contains(gdk::ModifierType::ALT_MASK))
+}
+
 pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> String {
     let mut val = in_str;
     let mut state = in_state;
@@ -19,7 +35,7 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
     if state.contains(gdk::ModifierType::CONTROL_MASK)
         && !state.contains(gdk::ModifierType::SHIFT_MASK)
         && !state.contains(gdk::ModifierType::ALT_MASK)
-        && !state.contains(gdk::ModifierType::META_MASK)
+        && !has_super(state)
     {
         if val == "6" {
             val = "^";
@@ -30,6 +46,7 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
 
     let chars: Vec<char> = in_str.chars().collect();
 
+    let lowercase;
     if chars.len() == 1 {
         let ch = chars[0];
 
@@ -37,22 +54,35 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
         if ch.is_ascii() && !ch.is_alphanumeric() {
             state.remove(gdk::ModifierType::SHIFT_MASK);
         }
+
+        // Neovim treats <C-A> like <C-a>, so send the lowercase letter with an explicit Shift
+        // to tell <C-S-a> apart
+        if ch.is_uppercase()
+            && state.contains(gdk::ModifierType::SHIFT_MASK)
+            && (state.intersects(SHORTCUT_MODIFIERS) || has_super(state))
+        {
+            lowercase = ch.to_lowercase().to_string();
+            val = &lowercase;
+        }
     }
 
     if val == "<" {
         val = "lt";
     }
 
-    let mut mod_chars = Vec::<&str>::with_capacity(3);
+    let mut mod_chars = Vec::<&str>::with_capacity(4);
     if state.contains(gdk::ModifierType::SHIFT_MASK) {
         mod_chars.push("S");
     }
     if state.contains(gdk::ModifierType::CONTROL_MASK) {
         mod_chars.push("C");
     }
-    if state.contains(gdk::ModifierType::ALT_MASK) || state.contains(gdk::ModifierType::META_MASK) {
+    if state.contains(gdk::ModifierType::ALT_MASK) {
         mod_chars.push("A");
     }
+    if has_super(state) {
+        mod_chars.push("D");
+    }
 
     let sep = if empty { "" } else { "-" };
     let input = [mod_chars.as_slice(), &[val]].concat().join(sep);
@@ -76,16 +106,67 @@ pub fn convert_key(keyval: gdk::Key, modifiers: gdk::ModifierType) -> Option<Str
         .map(|ch| keyval_to_input_string(&ch.to_string(), modifiers))
 }
 
-pub fn im_input(nvim: &NvimSession, input: &str) {
-    debug!("nvim_input -> {input}");
+/// The keyval a key has in the first Latin group of the keymap, in the same shift level. `keys`
+/// are all the keyvals the key has, from `gdk::Display::map_keycode()`.
+///
+/// This is only used for shortcuts typed on a non-Latin layout, so that e.g. Ctrl+ф on a Russian
+/// layout becomes `<C-a>`. Other Latin layouts like AZERTY keep their own keys.
+fn latin_keyval(
+    keyval: gdk::Key,
+    modifiers: gdk::ModifierType,
+    keys: &[(gdk::KeymapKey, gdk::Key)],
+) -> Option<gdk::Key> {
+    if !modifiers.intersects(SHORTCUT_MODIFIERS)
+        || keyval.to_unicode().is_none_or(|ch| ch.is_ascii())
+    {
+        return None;
+    }
+
+    let level = i32::from(modifiers.contains(gdk::ModifierType::SHIFT_MASK));
+    keys.iter()
+        .filter(|(key, keyval)| {
+            key.level() == level && keyval.to_unicode().is_some_and(|ch| ch.is_ascii_graphic())
+        })
+        .min_by_key(|(key, _)| key.group())
+        .map(|&(_, keyval)| keyval)
+}
+
+/// The keyval to convert for a key press, falling back to a Latin layout for shortcuts
+pub fn shortcut_keyval(
+    display: &gdk::Display,
+    keyval: gdk::Key,
+    keycode: u32,
+    modifiers: gdk::ModifierType,
+) -> gdk::Key {
+    display
+        .map_keycode(keycode)
+        .and_then(|keys| latin_keyval(keyval, modifiers, &keys))
+        .unwrap_or(keyval)
+}
 
+pub fn im_input(nvim: &NvimSession, input: &str) {
     let input: String = input
         .chars()
         .map(|ch| keyval_to_input_string(&ch.to_string(), gdk::ModifierType::empty()))
         .collect();
-    nvim.block_timeout(nvim.input(&input))
-        .ok_and_report()
-        .expect("Failed to send input command to nvim");
+    nvim.queue_input(input);
+}
+
+/// The keys deleting `n_chars` characters starting `offset` characters away from the cursor in
+/// insert mode, leaving the cursor where it was relative to the rest of the text
+pub fn delete_surrounding_input(offset: i32, n_chars: i32) -> String {
+    let n_chars = n_chars.max(0);
+    let (motion, back) = if offset < 0 {
+        // Anything deleted before the cursor moves it back along with the text
+        (
+            "<Left>",
+            "<Right>".repeat((-offset - n_chars).max(0) as usize),
+        )
+    } else {
+        ("<Right>", "<Left>".repeat(offset as usize))
+    };
+
+    motion.repeat(offset.unsigned_abs() as usize) + &"<Del>".repeat(n_chars as usize) + &back
 }
 
 pub fn gtk_key_press(
@@ -94,10 +175,7 @@ pub fn gtk_key_press(
     modifiers: gdk::ModifierType,
 ) -> glib::Propagation {
     if let Some(input) = convert_key(keyval, modifiers) {
-        debug!("nvim_input -> {input}");
-        nvim.block_timeout(nvim.input(&input))
-            .ok_and_report()
-            .expect("Failed to send input command to nvim");
+        nvim.queue_input(input);
         glib::Propagation::Stop
     } else {
         glib::Propagation::Proceed
@@ -128,13 +206,93 @@ mod tests {
             "<" == "<lt>";
             "", SHIFT_MASK == "S";
             "", SHIFT_MASK | CONTROL_MASK | ALT_MASK == "SCA";
+            "", CONTROL_MASK | SUPER_MASK == "CD";
             "a", SHIFT_MASK == "<S-a>";
             "a", SHIFT_MASK | CONTROL_MASK | ALT_MASK == "<S-C-A-a>";
             "6", CONTROL_MASK == "<C-^>";
-            "6", CONTROL_MASK | META_MASK == "<C-A-6>";
+            "6", CONTROL_MASK | META_MASK == "<C-D-6>";
+            "6", CONTROL_MASK | ALT_MASK | META_MASK == "<C-A-6>";
             "2", CONTROL_MASK == "<C-@>";
             "2", CONTROL_MASK | ALT_MASK == "<C-A-2>";
-            "j", SUPER_MASK == "j";
+            "j", SUPER_MASK == "<D-j>";
+            "j", HYPER_MASK == "<D-j>";
+            "j", META_MASK == "<D-j>";
+            "j", ALT_MASK | META_MASK == "<A-j>";
+            "A", SHIFT_MASK == "<S-A>";
+            "A", SHIFT_MASK | CONTROL_MASK == "<S-C-a>";
+            "A", SHIFT_MASK | SUPER_MASK == "<S-D-a>";
+            "k0" == "<k0>";
+            "kEnter", CONTROL_MASK == "<C-kEnter>";
+            "ф", CONTROL_MASK == "<C-ф>";
+            "ф", ALT_MASK == "<A-ф>";
+            "Ф", SHIFT_MASK | CONTROL_MASK == "<S-C-ф>";
         }
     }
+
+    #[test]
+    fn test_delete_surrounding_input() {
+        // Before the cursor
+        assert_eq!("<Left><Left><Del><Right>", delete_surrounding_input(-2, 1));
+        assert_eq!("<Left><Left><Del><Del>", delete_surrounding_input(-2, 2));
+        // Across the cursor
+        assert_eq!(
+            "<Left><Left><Del><Del><Del>",
+            delete_surrounding_input(-2, 3)
+        );
+        // After the cursor
+        assert_eq!("<Del><Del>", delete_surrounding_input(0, 2));
+        assert_eq!(
+            "<Right><Right><Del><Left><Left>",
+            delete_surrounding_input(2, 1)
+        );
+    }
+
+    #[test]
+    fn test_latin_keyval() {
+        use gdk::{Key, KeymapKey, ModifierType};
+
+        // The key for "a" on a keymap with a Russian and a US group
+        let keys = [
+            (KeymapKey::new(38, 0, 0), Key::Cyrillic_ef),
+            (KeymapKey::new(38, 0, 1), Key::Cyrillic_EF),
+            (KeymapKey::new(38, 1, 0), Key::a),
+            (KeymapKey::new(38, 1, 1), Key::A),
+        ];
+        let latin = |keyval, modifiers| {
+            latin_keyval(keyval, modifiers, &keys).map(|keyval| {
+                keyval_to_input_string(&keyval.to_unicode().unwrap().to_string(), modifiers)
+            })
+        };
+
+        assert_eq!(
+            Some("<C-a>".to_owned()),
+            latin(Key::Cyrillic_ef, ModifierType::CONTROL_MASK)
+        );
+        assert_eq!(
+            Some("<A-a>".to_owned()),
+            latin(Key::Cyrillic_ef, ModifierType::ALT_MASK)
+        );
+        assert_eq!(
+            Some("<S-C-a>".to_owned()),
+            latin(
+                Key::Cyrillic_EF,
+                ModifierType::SHIFT_MASK | ModifierType::CONTROL_MASK
+            )
+        );
+
+        // Plain and shifted keys are text, and Latin layouts are left alone
+        assert_eq!(None, latin(Key::Cyrillic_ef, ModifierType::empty()));
+        assert_eq!(None, latin(Key::Cyrillic_EF, ModifierType::SHIFT_MASK));
+        assert_eq!(None, latin(Key::a, ModifierType::CONTROL_MASK));
+
+        // Keys without a Latin keyval keep their own
+        assert_eq!(
+            None,
+            latin_keyval(
+                Key::Greek_alpha,
+                ModifierType::CONTROL_MASK,
+                &[(KeymapKey::new(38, 0, 0), Key::Greek_alpha)]
+            )
+        );
+    }
 }
@@ -19,10 +19,14 @@ mod file_browser;
 mod grid;
 mod highlight;
 mod input;
+mod menubar;
+mod messages;
 mod misc;
 mod nvim;
+mod nvim_menu;
 mod nvim_viewport;
 mod plug_manager;
+mod pointer;
 mod popup_menu;
 mod project;
 mod render;
@@ -195,7 +199,8 @@ pub struct Args {
     /// limited to arguments that:
     ///
     /// * Don't cause a user prompt, e.g. anything that makes nvim go "Hit ENTER...", either
-    ///   directly or indirectly
+    ///   directly or indirectly. This doesn't apply with versions of nvim that support
+    ///   ext_messages, since prompts are shown as dialogs instead
     ///
     /// * Don't interfere with stdio output (since we start nvim with --embed, we need stdio
     ///   reserved for RPC)
@@ -0,0 +1,216 @@
+use std::{cell::Cell, cell::RefCell, ops::Deref, path::Path, rc::Rc, sync::Arc};
+
+use gio::prelude::*;
+use gtk::prelude::*;
+
+use log::error;
+
+use crate::nvim_menu::{self, MenuBuilder, MenuCommand, MenuItem};
+use crate::shell;
+use crate::ui::UiMutex;
+
+const ACTION_GROUP: &str = "menubar";
+const RUN_ACTION: &str = "menubar.run";
+
+/// Icons for the builtin toolbar items of Vim
+const TOOLBAR_ICONS: &[(&str, &str)] = &[
+    ("New", "document-new"),
+    ("Open", "document-open"),
+    ("Save", "document-save"),
+    ("SaveAll", "document-save-as"),
+    ("Print", "document-print"),
+    ("Undo", "edit-undo"),
+    ("Redo", "edit-redo"),
+    ("Cut", "edit-cut"),
+    ("Copy", "edit-copy"),
+    ("Paste", "edit-paste"),
+    ("Replace", "edit-find-replace"),
+    ("FindNext", "go-down"),
+    ("FindPrev", "go-up"),
+    ("Make", "system-run"),
+    ("RunScript", "media-playback-start"),
+    ("Help", "help-browser"),
+    ("FindHelp", "help-contents"),
+];
+
+/// Top level menus that have a special meaning, and aren't shown in the menubar. Menus starting
+/// with `]` are hidden toolbar menus.
+fn is_menubar_menu(item: &MenuItem) -> bool {
+    !matches!(item.name.as_str(), "PopUp" | "ToolBar") && !item.name.starts_with(']')
+}
+
+fn toolbar_icon(item: &MenuItem) -> Option<gtk::Image> {
+    if let Some(ref icon) = item.icon {
+        return Some(if Path::new(icon).is_absolute() {
+            gtk::Image::from_file(icon)
+        } else {
+            gtk::Image::from_icon_name(icon)
+        });
+    }
+
+    TOOLBAR_ICONS
+        .iter()
+        .find(|(name, _)| *name == item.name)
+        .map(|(_, icon)| gtk::Image::from_icon_name(icon))
+}
+
+/// The menubar and the toolbar, generated from the `:menu` definitions in Neovim
+pub struct MenuBars {
+    container: gtk::Box,
+    menubar: gtk::PopoverMenuBar,
+    toolbar: gtk::Box,
+    commands: Rc<RefCell<Vec<MenuCommand>>>,
+    menubar_enabled: Cell<bool>,
+    toolbar_enabled: Cell<bool>,
+}
+
+impl MenuBars {
+    pub fn new(state: &Arc<UiMutex<shell::State>>) -> Self {
+        let container = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .visible(false)
+            .build();
+        let menubar = gtk::PopoverMenuBar::from_model(None::<&gio::MenuModel>);
+        let toolbar = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .css_classes(vec!["toolbar".to_string()])
+            .visible(false)
+            .build();
+        container.append(&menubar);
+        container.append(&toolbar);
+
+        let commands = Rc::new(RefCell::new(Vec::<MenuCommand>::new()));
+        let run_action = gio::SimpleAction::new("run", Some(glib::VariantTy::UINT32));
+        run_action.connect_activate(glib::clone!(
+            #[weak]
+            state,
+            #[weak]
+            commands,
+            move |_, idx| {
+                let Some(idx) = idx.and_then(u32::from_variant) else {
+                    return;
+                };
+                // Don't hold onto the command list while Neovim runs the command, it might
+                // trigger a rebuild of the menus
+                let command = commands.borrow().get(idx as usize).cloned();
+                if let Some(command) = command
+                    && let Some(nvim) = state.borrow().nvim()
+                {
+                    nvim_menu::run_command(&nvim, &command);
+                }
+            }
+        ));
+        let actions = gio::SimpleActionGroup::new();
+        actions.add_action(&run_action);
+        container.insert_action_group(ACTION_GROUP, Some(&actions));
+
+        MenuBars {
+            container,
+            menubar,
+            toolbar,
+            commands,
+            menubar_enabled: Cell::new(true),
+            toolbar_enabled: Cell::new(true),
+        }
+    }
+
+    /// Rebuild the menubar and toolbar from the JSON encoded result of `menu_get('')`, and the
+    /// current `mode()`
+    pub fn update_from_json(&self, menus: &str, mode: &str) {
+        match nvim_menu::parse_menu_json(menus, mode) {
+            Ok(menus) => self.update(menus),
+            Err(err) => error!("Can't parse the menus: {err}"),
+        }
+    }
+
+    pub fn update(&self, menus: Vec<MenuItem>) {
+        let (menus, special): (Vec<_>, Vec<_>) = menus.into_iter().partition(is_menubar_menu);
+        let mut builder = MenuBuilder::new(RUN_ACTION);
+
+        // GTK only shows submenus in the menubar, so top level items without one are dropped
+        let model = gio::Menu::new();
+        for menu in menus.iter().filter(|menu| !menu.submenus.is_empty()) {
+            model.append_submenu(Some(&menu.label()), &builder.build(&menu.submenus));
+        }
+        self.menubar.set_menu_model(Some(&model));
+
+        while let Some(child) = self.toolbar.first_child() {
+            self.toolbar.remove(&child);
+        }
+        let toolbar_items = special
+            .into_iter()
+            .find(|menu| menu.name == "ToolBar")
+            .map(|menu| menu.submenus)
+            .unwrap_or_default();
+        for item in &toolbar_items {
+            if item.is_separator() {
+                self.toolbar
+                    .append(&gtk::Separator::new(gtk::Orientation::Vertical));
+            } else if let Some(ref command) = item.command {
+                let target = item.enabled.then(|| builder.push_command(command.clone()));
+                self.toolbar
+                    .append(&toolbar_button(item, builder.action(), target));
+            }
+        }
+
+        *self.commands.borrow_mut() = builder.into_commands();
+        self.update_visibility();
+    }
+
+    pub fn set_menubar_enabled(&self, enabled: bool) {
+        self.menubar_enabled.set(enabled);
+        self.update_visibility();
+    }
+
+    pub fn set_toolbar_enabled(&self, enabled: bool) {
+        self.toolbar_enabled.set(enabled);
+        self.update_visibility();
+    }
+
+    /// Only show the bars when there's something in them, as Neovim doesn't define any menus
+    /// besides the PopUp menu by default
+    fn update_visibility(&self) {
+        let has_menus = self
+            .menubar
+            .menu_model()
+            .is_some_and(|model| model.n_items() > 0);
+        self.menubar
+            .set_visible(self.menubar_enabled.get() && has_menus);
+        self.toolbar
+            .set_visible(self.toolbar_enabled.get() && self.toolbar.first_child().is_some());
+
+        self.container
+            .set_visible(self.menubar.is_visible() || self.toolbar.is_visible());
+    }
+}
+
+fn toolbar_button(item: &MenuItem, action: &str, target: Option<u32>) -> gtk::Button {
+    let button = gtk::Button::builder()
+        .focusable(false)
+        .has_frame(false)
+        .sensitive(target.is_some())
+        .build();
+
+    match toolbar_icon(item) {
+        Some(icon) => button.set_child(Some(&icon)),
+        None => {
+            button.set_label(&item.label());
+            button.set_use_underline(true);
+        }
+    }
+    button.set_tooltip_text(Some(item.tooltip.as_deref().unwrap_or(&item.name)));
+
+    if let Some(target) = target {
+        button.set_action_name(Some(action));
+        button.set_action_target_value(Some(&target.to_variant()));
+    }
+    button
+}
+
+impl Deref for MenuBars {
+    type Target = gtk::Box;
+
+    fn deref(&self) -> &gtk::Box {
+        &self.container
+    }
+}
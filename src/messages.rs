@@ -0,0 +1,493 @@
+use std::ops::Deref;
+use std::time::Duration;
+
+use gtk::prelude::*;
+
+use crate::highlight::HighlightMap;
+use crate::render;
+use crate::shell::RenderState;
+
+/// The most toasts we show at once, older ones get dropped first
+const MAX_TOASTS: usize = 5;
+/// The most messages we keep around in the message history popover
+const MAX_HISTORY: usize = 500;
+
+const TOAST_TIMEOUT: Duration = Duration::from_secs(4);
+const ERROR_TOAST_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageChunk {
+    pub hl_id: u64,
+    pub text: String,
+}
+
+/// How severe a message is, derived from the kind Neovim reports for it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl MessageLevel {
+    pub fn from_kind(kind: &str) -> Self {
+        match kind {
+            "emsg" | "echoerr" | "lua_error" | "rpc_error" => MessageLevel::Error,
+            "wmsg" => MessageLevel::Warning,
+            _ => MessageLevel::Info,
+        }
+    }
+
+    fn css_class(self) -> &'static str {
+        match self {
+            MessageLevel::Info => "nvim-message-info",
+            MessageLevel::Warning => "nvim-message-warning",
+            MessageLevel::Error => "nvim-message-error",
+        }
+    }
+
+    fn icon_name(self) -> Option<&'static str> {
+        match self {
+            MessageLevel::Info => None,
+            MessageLevel::Warning => Some("dialog-warning-symbolic"),
+            MessageLevel::Error => Some("dialog-error-symbolic"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub kind: String,
+    pub content: Vec<MessageChunk>,
+}
+
+impl Message {
+    pub fn new(kind: String, content: Vec<MessageChunk>) -> Self {
+        Message { kind, content }
+    }
+
+    pub fn level(&self) -> MessageLevel {
+        MessageLevel::from_kind(&self.kind)
+    }
+
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .map(|chunk| chunk.text.as_str())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content
+            .iter()
+            .all(|chunk| chunk.text.trim().is_empty())
+    }
+
+    /// Convert the highlights of each chunk into pango attributes for the text returned by
+    /// [`Message::text`]
+    fn attributes(&self, hl: &HighlightMap) -> pango::AttrList {
+        let attrs = pango::AttrList::new();
+        let mut start = 0;
+
+        for chunk in &self.content {
+            let end = start + chunk.text.len() as u32;
+            let chunk_hl = hl.get(Some(chunk.hl_id));
+
+            let insert = |mut attr: pango::Attribute| {
+                attr.set_start_index(start);
+                attr.set_end_index(end);
+                attrs.insert(attr);
+            };
+            if let Some(fg) = chunk_hl.foreground {
+                insert(fg.to_pango_fg().into());
+            }
+            if chunk_hl.bold {
+                insert(pango::AttrInt::new_weight(pango::Weight::Bold).into());
+            }
+            if chunk_hl.italic {
+                insert(pango::AttrInt::new_style(pango::Style::Italic).into());
+            }
+            if chunk_hl.undercurl {
+                insert(pango::AttrInt::new_underline(pango::Underline::Error).into());
+            } else if chunk_hl.underline {
+                insert(pango::AttrInt::new_underline(pango::Underline::Single).into());
+            }
+
+            start = end;
+        }
+
+        attrs
+    }
+
+    fn create_widget(&self, hl: &HighlightMap) -> gtk::Box {
+        let level = self.level();
+        let base = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        base.add_css_class("nvim-message");
+        base.add_css_class(level.css_class());
+
+        if let Some(icon_name) = level.icon_name() {
+            let icon = gtk::Image::from_icon_name(icon_name);
+            icon.set_valign(gtk::Align::Start);
+            base.append(&icon);
+        }
+
+        let label = gtk::Label::builder()
+            .label(self.text().trim_end())
+            .attributes(&self.attributes(hl))
+            .wrap(true)
+            .wrap_mode(pango::WrapMode::WordChar)
+            .max_width_chars(100)
+            .xalign(0.0)
+            .build();
+        base.append(&label);
+
+        base
+    }
+}
+
+/// Displays Neovim's messages as toasts stacked over the viewport when ext_messages is enabled,
+/// along with a popover containing the message history
+pub struct MessageArea {
+    toasts: gtk::Box,
+    history_popover: gtk::Popover,
+    history_list: gtk::ListBox,
+    history_len: usize,
+    css_provider: gtk::CssProvider,
+}
+
+impl MessageArea {
+    pub fn new() -> Self {
+        let toasts = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .valign(gtk::Align::End)
+            .margin_end(12)
+            .margin_bottom(12)
+            .can_target(false)
+            .can_focus(false)
+            .build();
+
+        let history_list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        history_list.add_css_class("nvim-message-history");
+        history_list.set_placeholder(Some(&gtk::Label::new(Some("No messages"))));
+
+        let history_scroll = gtk::ScrolledWindow::builder()
+            .propagate_natural_width(true)
+            .propagate_natural_height(true)
+            .max_content_height(400)
+            .max_content_width(800)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .child(&history_list)
+            .build();
+
+        let history_popover = gtk::Popover::builder()
+            .autohide(true)
+            .child(&history_scroll)
+            .build();
+        history_popover.add_css_class("nvim-popover");
+
+        let css_provider = gtk::CssProvider::new();
+        gtk::style_context_add_provider_for_display(
+            &toasts.display(),
+            &css_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+
+        MessageArea {
+            toasts,
+            history_popover,
+            history_list,
+            history_len: 0,
+            css_provider,
+        }
+    }
+
+    pub fn history_popover(&self) -> &gtk::Popover {
+        &self.history_popover
+    }
+
+    /// Match the default colors and font of the viewport, needs to be called when they change
+    pub fn update_css(&self, render_state: &RenderState) {
+        update_css(
+            &self.css_provider,
+            ".nvim-message",
+            &render_state.hl,
+            &render_state.font_ctx,
+        );
+    }
+
+    /// Show a new message as a toast, and record it in the history
+    pub fn show(&mut self, message: &Message, replace_last: bool, render_state: &RenderState) {
+        let hl = &render_state.hl;
+        if replace_last {
+            if let Some(last) = self.toasts.last_child() {
+                self.toasts.remove(&last);
+            }
+            if let Some(last) = self
+                .history_len
+                .checked_sub(1)
+                .and_then(|idx| self.history_list.row_at_index(idx as i32))
+            {
+                self.history_list.remove(&last);
+                self.history_len -= 1;
+            }
+        }
+
+        if message.is_empty() {
+            return;
+        }
+
+        self.push_history(message, hl);
+
+        let toast = message.create_widget(hl);
+        self.toasts.append(&toast);
+        while self.toasts.observe_children().n_items() as usize > MAX_TOASTS {
+            self.toasts.remove(&self.toasts.first_child().unwrap());
+        }
+
+        let timeout = match message.level() {
+            MessageLevel::Error => ERROR_TOAST_TIMEOUT,
+            _ => TOAST_TIMEOUT,
+        };
+        glib::timeout_add_local_once(
+            timeout,
+            glib::clone!(
+                #[weak(rename_to = toasts)]
+                self.toasts,
+                #[weak]
+                toast,
+                move || {
+                    if toast.parent().as_ref() == Some(toasts.upcast_ref()) {
+                        toasts.remove(&toast);
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Remove all of the toasts currently on screen
+    pub fn clear(&self) {
+        while let Some(child) = self.toasts.first_child() {
+            self.toasts.remove(&child);
+        }
+    }
+
+    /// Replace the message history with the one from Neovim, and show it
+    pub fn show_history(&mut self, messages: &[Message], render_state: &RenderState) {
+        let hl = &render_state.hl;
+        self.clear_history();
+        for message in messages.iter().filter(|message| !message.is_empty()) {
+            self.push_history(message, hl);
+        }
+
+        // Without a header bar there's no button to attach the popover to, so just show it over
+        // the viewport instead
+        if self.history_popover.parent().is_none() {
+            match self.toasts.parent() {
+                Some(parent) => self.history_popover.set_parent(&parent),
+                None => return,
+            }
+        }
+        self.history_popover.popup();
+    }
+
+    pub fn clear_history(&mut self) {
+        while let Some(row) = self.history_list.row_at_index(0) {
+            self.history_list.remove(&row);
+        }
+        self.history_len = 0;
+    }
+
+    fn push_history(&mut self, message: &Message, hl: &HighlightMap) {
+        self.history_list.append(&message.create_widget(hl));
+        self.history_len += 1;
+
+        if self.history_len > MAX_HISTORY {
+            if let Some(first) = self.history_list.row_at_index(0) {
+                self.history_list.remove(&first);
+            }
+            self.history_len -= 1;
+        }
+    }
+}
+
+impl Deref for MessageArea {
+    type Target = gtk::Box;
+
+    fn deref(&self) -> &gtk::Box {
+        &self.toasts
+    }
+}
+
+/// A thin strip below the viewport showing the mode, pending keys and ruler that Neovim would
+/// otherwise draw on the last screen row
+pub struct StatusStrip {
+    base: gtk::Box,
+    mode: gtk::Label,
+    showcmd: gtk::Label,
+    ruler: gtk::Label,
+    css_provider: gtk::CssProvider,
+    /// Whether the user wants the strip to be shown
+    enabled: bool,
+    /// Whether we've received any status messages, which only happens with ext_messages
+    active: bool,
+}
+
+impl StatusStrip {
+    pub fn new() -> Self {
+        let base = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(12)
+            .visible(false)
+            .can_focus(false)
+            .build();
+        base.add_css_class("nvim-status-strip");
+
+        let new_label = |xalign, hexpand| {
+            gtk::Label::builder()
+                .xalign(xalign)
+                .hexpand(hexpand)
+                .single_line_mode(true)
+                .ellipsize(pango::EllipsizeMode::End)
+                .build()
+        };
+        let mode = new_label(0.0, true);
+        let showcmd = new_label(1.0, false);
+        let ruler = new_label(1.0, false);
+        ruler.set_width_chars(18);
+
+        base.append(&mode);
+        base.append(&showcmd);
+        base.append(&ruler);
+
+        let css_provider = gtk::CssProvider::new();
+        gtk::style_context_add_provider_for_display(
+            &base.display(),
+            &css_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+
+        StatusStrip {
+            base,
+            mode,
+            showcmd,
+            ruler,
+            css_provider,
+            enabled: true,
+            active: false,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.base.set_visible(self.enabled && self.active);
+    }
+
+    pub fn set_mode(&mut self, content: &Message, render_state: &RenderState) {
+        self.activate();
+        set_label_content(&self.mode, content, &render_state.hl);
+    }
+
+    pub fn set_showcmd(&mut self, content: &Message, render_state: &RenderState) {
+        self.activate();
+        set_label_content(&self.showcmd, content, &render_state.hl);
+    }
+
+    pub fn set_ruler(&mut self, content: &Message, render_state: &RenderState) {
+        self.activate();
+        set_label_content(&self.ruler, content, &render_state.hl);
+    }
+
+    /// Match the default colors and font of the viewport, needs to be called when they change
+    pub fn update_css(&self, render_state: &RenderState) {
+        update_css(
+            &self.css_provider,
+            ".nvim-status-strip",
+            &render_state.hl,
+            &render_state.font_ctx,
+        );
+    }
+
+    fn activate(&mut self) {
+        if !self.active {
+            self.active = true;
+            self.base.set_visible(self.enabled);
+        }
+    }
+}
+
+fn set_label_content(label: &gtk::Label, content: &Message, hl: &HighlightMap) {
+    label.set_label(&content.text());
+    label.set_attributes(Some(&content.attributes(hl)));
+}
+
+impl Deref for StatusStrip {
+    type Target = gtk::Box;
+
+    fn deref(&self) -> &gtk::Box {
+        &self.base
+    }
+}
+
+/// Style widgets matching selector with the default colors and font of the viewport
+fn update_css(
+    css_provider: &gtk::CssProvider,
+    selector: &str,
+    hl: &HighlightMap,
+    font_ctx: &render::Context,
+) {
+    let font_desc = font_ctx.font_description();
+
+    css_provider.load_from_data(&format!(
+        "{selector} {{\
+                background-color: {bg};\
+                color: {fg};\
+                font-family: \"{font}\";\
+                font-size: {size}pt;\
+            }}",
+        fg = hl.fg().to_hex(),
+        bg = hl.bg().to_hex(),
+        font = font_desc.family().unwrap().as_str(),
+        size = (font_desc.size() as f64 / pango::SCALE as f64),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_level() {
+        assert_eq!(MessageLevel::Error, MessageLevel::from_kind("emsg"));
+        assert_eq!(MessageLevel::Error, MessageLevel::from_kind("lua_error"));
+        assert_eq!(MessageLevel::Warning, MessageLevel::from_kind("wmsg"));
+        assert_eq!(MessageLevel::Info, MessageLevel::from_kind("echomsg"));
+        assert_eq!(MessageLevel::Info, MessageLevel::from_kind(""));
+    }
+
+    #[test]
+    fn test_message_text() {
+        let message = Message::new(
+            "echo".to_owned(),
+            vec![
+                MessageChunk {
+                    hl_id: 0,
+                    text: "Hello ".to_owned(),
+                },
+                MessageChunk {
+                    hl_id: 1,
+                    text: "world".to_owned(),
+                },
+            ],
+        );
+        assert_eq!("Hello world", message.text());
+        assert!(!message.is_empty());
+        assert!(Message::new("".to_owned(), vec![]).is_empty());
+    }
+}
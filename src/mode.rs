@@ -6,6 +6,8 @@ use std::collections::HashMap;
 pub enum NvimMode {
     Normal,
     Insert,
+    Cmdline,
+    Terminal,
     Other,
 }
 
@@ -28,6 +30,10 @@ impl Mode {
         self.mode == *mode
     }
 
+    pub fn is_cmdline_or_terminal(&self) -> bool {
+        matches!(self.mode, NvimMode::Cmdline | NvimMode::Terminal)
+    }
+
     pub fn mode_info(&self) -> Option<&ModeInfo> {
         self.info.as_ref().and_then(|i| i.get(self.idx))
     }
@@ -36,6 +42,8 @@ impl Mode {
         match mode {
             "normal" => self.mode = NvimMode::Normal,
             "insert" => self.mode = NvimMode::Insert,
+            "terminal" => self.mode = NvimMode::Terminal,
+            _ if mode.starts_with("cmdline") => self.mode = NvimMode::Cmdline,
             _ => self.mode = NvimMode::Other,
         }
 
@@ -77,11 +85,26 @@ impl CursorShape {
     }
 }
 
+/// How the cursor blinks in a mode, in milliseconds
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BlinkTimings {
+    pub wait: u32,
+    pub on: u32,
+    pub off: u32,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ModeInfo {
     cursor_shape: Option<CursorShape>,
     cell_percentage: Option<u64>,
     pub blinkwait: Option<u32>,
+    pub blinkon: Option<u32>,
+    pub blinkoff: Option<u32>,
+    /// The highlight of the cursor, 0 means the colors of the cell get reversed
+    pub attr_id: Option<u64>,
+    /// The highlight of the cursor while langmap ('iminsert') is active
+    pub attr_id_lm: Option<u64>,
+    pub mouse_shape: Option<u64>,
 }
 
 impl ModeInfo {
@@ -91,16 +114,17 @@ impl ModeInfo {
         } else {
             None
         };
+        let get_u64 = |key| mode_info_map.get(key).and_then(Value::as_u64);
 
         Ok(ModeInfo {
             cursor_shape,
-            cell_percentage: mode_info_map
-                .get("cell_percentage")
-                .and_then(|cp| cp.as_u64()),
-            blinkwait: mode_info_map
-                .get("blinkwait")
-                .and_then(|cp| cp.as_u64())
-                .map(|v| v as u32),
+            cell_percentage: get_u64("cell_percentage"),
+            blinkwait: get_u64("blinkwait").map(|v| v as u32),
+            blinkon: get_u64("blinkon").map(|v| v as u32),
+            blinkoff: get_u64("blinkoff").map(|v| v as u32),
+            attr_id: get_u64("attr_id"),
+            attr_id_lm: get_u64("attr_id_lm"),
+            mouse_shape: get_u64("mouse_shape"),
         })
     }
 
@@ -111,4 +135,72 @@ impl ModeInfo {
     pub fn cell_percentage(&self) -> u64 {
         self.cell_percentage.unwrap_or(0)
     }
+
+    /// The highlight to draw the cursor with, `None` if 'guicursor' doesn't set one
+    pub fn attr_id(&self, langmap: bool) -> Option<u64> {
+        if langmap {
+            self.attr_id_lm.or(self.attr_id)
+        } else {
+            self.attr_id
+        }
+    }
+
+    /// The blink timings of this mode. Like in Neovim, the cursor doesn't blink if any of them is
+    /// missing or zero.
+    pub fn blink(&self) -> Option<BlinkTimings> {
+        match (self.blinkwait, self.blinkon, self.blinkoff) {
+            (Some(wait), Some(on), Some(off)) if wait > 0 && on > 0 && off > 0 => {
+                Some(BlinkTimings { wait, on, off })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode_info(values: &[(&str, Value)]) -> ModeInfo {
+        let map = values
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), value.clone()))
+            .collect();
+        ModeInfo::new(&map).unwrap()
+    }
+
+    #[test]
+    fn test_blink_timings() {
+        let info = mode_info(&[
+            ("blinkwait", Value::from(700)),
+            ("blinkon", Value::from(400)),
+            ("blinkoff", Value::from(250)),
+        ]);
+        assert_eq!(
+            Some(BlinkTimings {
+                wait: 700,
+                on: 400,
+                off: 250
+            }),
+            info.blink()
+        );
+
+        let info = mode_info(&[
+            ("blinkwait", Value::from(700)),
+            ("blinkon", Value::from(0)),
+            ("blinkoff", Value::from(250)),
+        ]);
+        assert_eq!(None, info.blink());
+        assert_eq!(None, mode_info(&[]).blink());
+    }
+
+    #[test]
+    fn test_attr_id() {
+        let info = mode_info(&[("attr_id", Value::from(5)), ("attr_id_lm", Value::from(6))]);
+        assert_eq!(Some(5), info.attr_id(false));
+        assert_eq!(Some(6), info.attr_id(true));
+
+        let info = mode_info(&[("attr_id", Value::from(5))]);
+        assert_eq!(Some(5), info.attr_id(true));
+    }
 }
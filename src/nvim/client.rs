@@ -13,6 +13,8 @@ pub struct NeovimApiInfo {
     pub ext_popupmenu: bool,
     pub ext_tabline: bool,
     pub ext_termcolors: bool,
+    pub ext_multigrid: bool,
+    pub ext_messages: bool,
 
     pub ui_pum_set_height: bool,
     pub ui_pum_set_bounds: bool,
@@ -64,6 +66,8 @@ impl NeovimApiInfo {
                 "ext_popupmenu" => self.ext_popupmenu = true,
                 "ext_tabline" => self.ext_tabline = true,
                 "ext_termcolors" => self.ext_termcolors = true,
+                "ext_multigrid" => self.ext_multigrid = true,
+                "ext_messages" => self.ext_messages = true,
                 _ => (),
             };
         }
@@ -13,7 +13,7 @@ use crate::nvim::{Neovim, NvimWriter};
 use crate::shell;
 use crate::ui::UiMutex;
 
-use super::redraw_handler::{self, PendingPopupMenu, RedrawMode};
+use super::redraw_handler::{self, PendingPopupMenu, RedrawEvent, RedrawMode};
 
 pub struct NvimHandler {
     shell: Arc<UiMutex<shell::State>>,
@@ -134,12 +134,12 @@ impl NvimHandler {
 #[derive(Default)]
 struct PendingRedraws {
     scheduled: bool,
-    batches: Vec<Vec<Value>>,
+    batches: Vec<Vec<RedrawEvent>>,
 }
 
 impl PendingRedraws {
-    fn enqueue(&mut self, params: Vec<Value>) -> bool {
-        self.batches.push(params);
+    fn enqueue(&mut self, events: Vec<RedrawEvent>) -> bool {
+        self.batches.push(events);
         if self.scheduled {
             false
         } else {
@@ -148,7 +148,7 @@ impl PendingRedraws {
         }
     }
 
-    fn take_pending(&mut self) -> Option<Vec<Vec<Value>>> {
+    fn take_pending(&mut self) -> Option<Vec<Vec<RedrawEvent>>> {
         self.scheduled = false;
         if self.batches.is_empty() {
             None
@@ -163,9 +163,11 @@ fn queue_redraw(
     pending_redraws: Arc<Mutex<PendingRedraws>>,
     params: Vec<Value>,
 ) {
+    // Decoding happens here on the RPC thread, so the UI thread only has to apply the events
+    let events = redraw_handler::decode_redraw(params);
     let should_schedule = {
         let mut pending_redraws = pending_redraws.lock().unwrap();
-        pending_redraws.enqueue(params)
+        pending_redraws.enqueue(events)
     };
 
     if !should_schedule {
@@ -185,86 +187,38 @@ fn queue_redraw(
             return;
         };
 
-        if let Err(msg) = call_redraw_handlers(pending_batches, &shell) {
-            error!("Error call function: {msg}");
-        }
+        call_redraw_handlers(pending_batches, &shell);
     });
 }
 
-fn call_redraw_handlers(
-    pending_batches: Vec<Vec<Value>>,
-    ui: &Arc<UiMutex<shell::State>>,
-) -> result::Result<(), String> {
+fn call_redraw_handlers(pending_batches: Vec<Vec<RedrawEvent>>, ui: &Arc<UiMutex<shell::State>>) {
     let mut repaint_mode = RedrawMode::Nothing;
     let mut pending_popupmenu = PendingPopupMenu::None;
 
     let mut ui_ref = ui.borrow_mut();
     for params in pending_batches {
-        let (call_repaint_mode, call_popupmenu) = process_redraw_batch(params, &mut ui_ref)?;
+        let (call_repaint_mode, call_popupmenu) = process_redraw_batch(params, &mut ui_ref);
         repaint_mode = repaint_mode.max(call_repaint_mode);
         pending_popupmenu.update(call_popupmenu);
     }
     ui_ref.queue_draw(repaint_mode);
-    drop(ui_ref);
-    ui.borrow().popupmenu_flush(pending_popupmenu);
-    Ok(())
+    ui_ref.queue_popupmenu(pending_popupmenu);
 }
 
 fn process_redraw_batch(
-    params: Vec<Value>,
+    events: Vec<RedrawEvent>,
     ui_ref: &mut shell::State,
-) -> result::Result<(RedrawMode, PendingPopupMenu), String> {
+) -> (RedrawMode, PendingPopupMenu) {
     let mut repaint_mode = RedrawMode::Nothing;
     let mut pending_popupmenu = PendingPopupMenu::None;
 
-    for ev in params {
-        let ev_args = match ev {
-            Value::Array(args) => args,
-            _ => {
-                error!("Unsupported event type: {ev:?}");
-                continue;
-            }
-        };
-        let mut args_iter = ev_args.into_iter();
-        let ev_name = match args_iter.next() {
-            Some(ev_name) => ev_name,
-            None => {
-                error!(
-                    "No name provided with redraw event, args: {:?}",
-                    args_iter.as_slice()
-                );
-                continue;
-            }
-        };
-        let ev_name = match ev_name.as_str() {
-            Some(ev_name) => ev_name,
-            None => {
-                error!(
-                    "Expected event name to be str, instead got {:?}. Args: {:?}",
-                    ev_name,
-                    args_iter.as_slice()
-                );
-                continue;
-            }
-        };
-
-        for local_args in args_iter {
-            let args = match local_args {
-                Value::Array(ar) => ar,
-                _ => vec![],
-            };
-
-            let (call_repaint_mode, call_popupmenu) =
-                match redraw_handler::call(ui_ref, ev_name, args) {
-                    Ok(mode) => mode,
-                    Err(desc) => return Err(format!("Event {ev_name}\n{desc}")),
-                };
-            repaint_mode = repaint_mode.max(call_repaint_mode);
-            pending_popupmenu.update(call_popupmenu);
-        }
+    for event in events {
+        let (call_repaint_mode, call_popupmenu) = redraw_handler::apply(ui_ref, event);
+        repaint_mode = repaint_mode.max(call_repaint_mode);
+        pending_popupmenu.update(call_popupmenu);
     }
 
-    Ok((repaint_mode, pending_popupmenu))
+    (repaint_mode, pending_popupmenu)
 }
 
 fn safe_call<F>(shell: Arc<UiMutex<shell::State>>, cb: F)
@@ -314,17 +268,17 @@ mod tests {
     fn pending_redraws_reschedule_after_current_batch_is_taken() {
         let mut pending = PendingRedraws::default();
 
-        assert!(pending.enqueue(vec![Value::Nil]));
-        assert!(!pending.enqueue(vec![Value::Nil]));
+        assert!(pending.enqueue(vec![RedrawEvent::Flush]));
+        assert!(!pending.enqueue(vec![RedrawEvent::Flush]));
         assert_eq!(2, pending.take_pending().unwrap().len());
 
         // Once the current idle callback has taken ownership of the pending redraws, newly queued
         // redraws must schedule the next idle callback.
-        assert!(pending.enqueue(vec![Value::Nil]));
-        assert!(!pending.enqueue(vec![Value::Nil]));
+        assert!(pending.enqueue(vec![RedrawEvent::Flush]));
+        assert!(!pending.enqueue(vec![RedrawEvent::Flush]));
         assert_eq!(2, pending.take_pending().unwrap().len());
 
         assert!(pending.take_pending().is_none());
-        assert!(pending.enqueue(vec![Value::Nil]));
+        assert!(pending.enqueue(vec![RedrawEvent::Flush]));
     }
 }
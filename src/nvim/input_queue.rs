@@ -0,0 +1,298 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use log::{debug, error, warn};
+
+use nvim_rs::Value;
+
+use tokio::{
+    runtime::Runtime,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+use super::{CallErrorExt, Neovim, SessionError, call_timeout};
+
+/// Scrolls the window under the given screen cell by an exact number of lines and columns. The
+/// topmost window wins when floats overlap. The command line and terminals scroll on their own,
+/// so nothing happens in those modes.
+const SCROLL_LUA: &str = r#"
+local row, col, lines, columns = ...
+local mode = vim.api.nvim_get_mode().mode:sub(1, 1)
+if mode == 'c' or mode == 't' then
+  return
+end
+local target, zindex = nil, -1
+for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+  local pos = vim.api.nvim_win_get_position(win)
+  local win_zindex = vim.api.nvim_win_get_config(win).zindex or 0
+  if row >= pos[1] and row < pos[1] + vim.api.nvim_win_get_height(win)
+    and col >= pos[2] and col < pos[2] + vim.api.nvim_win_get_width(win)
+    and win_zindex > zindex then
+    target, zindex = win, win_zindex
+  end
+end
+if target == nil then
+  return
+end
+vim.api.nvim_win_call(target, function()
+  if lines ~= 0 then
+    -- <C-e> and <C-y>, which scroll the window by lines
+    vim.cmd.normal({ math.abs(lines) .. (lines > 0 and '\x05' or '\x19'), bang = true })
+  end
+  if columns ~= 0 then
+    vim.cmd.normal({ math.abs(columns) .. (columns > 0 and 'zl' or 'zh'), bang = true })
+  end
+end)
+"#;
+
+/// The arguments of an `nvim_input_mouse()` call
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MouseInput {
+    pub button: &'static str,
+    pub action: &'static str,
+    pub modifier: String,
+    pub grid: i64,
+    pub row: i64,
+    pub col: i64,
+}
+
+/// A precise scroll of the window under a screen cell, from a touchpad
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScrollInput {
+    pub row: i64,
+    pub col: i64,
+    pub lines: f64,
+    pub columns: f64,
+}
+
+#[derive(Debug, PartialEq)]
+enum InputEvent {
+    Keys(String),
+    Mouse(MouseInput),
+    Scroll(ScrollInput),
+}
+
+/// Sends user input to Neovim from the session's runtime, so the UI never waits on it. Events are
+/// sent in the order they were queued, and keys that pile up while a request is in flight (key
+/// repeat, an input method committing a whole word) are sent with a single `nvim_input()` call.
+/// The same goes for scrolling, where the fraction of a line that's left over is kept for the
+/// next scroll.
+#[derive(Clone)]
+pub struct InputQueue {
+    sender: UnboundedSender<InputEvent>,
+}
+
+impl InputQueue {
+    pub fn new(nvim: Neovim, timeout: Duration, runtime: &Runtime) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        runtime.spawn(run(nvim, timeout, receiver));
+
+        InputQueue { sender }
+    }
+
+    pub fn keys(&self, keys: String) {
+        if !keys.is_empty() {
+            self.send(InputEvent::Keys(keys));
+        }
+    }
+
+    pub fn mouse(&self, mouse: MouseInput) {
+        self.send(InputEvent::Mouse(mouse));
+    }
+
+    pub fn scroll(&self, scroll: ScrollInput) {
+        self.send(InputEvent::Scroll(scroll));
+    }
+
+    fn send(&self, event: InputEvent) {
+        if let Err(err) = self.sender.send(event) {
+            debug!("Input queue is closed, dropping {:?}", err.0);
+        }
+    }
+}
+
+/// Take the next event off the queue, merging it with the keys or scrolls that directly follow it
+fn next_batch(queue: &mut VecDeque<InputEvent>) -> Option<InputEvent> {
+    let mut event = queue.pop_front()?;
+
+    match event {
+        InputEvent::Keys(ref mut keys) => {
+            while let Some(InputEvent::Keys(next)) = queue.front() {
+                keys.push_str(next);
+                queue.pop_front();
+            }
+        }
+        InputEvent::Scroll(ref mut scroll) => {
+            while let Some(InputEvent::Scroll(next)) = queue.front() {
+                scroll.lines += next.lines;
+                scroll.columns += next.columns;
+                queue.pop_front();
+            }
+        }
+        InputEvent::Mouse(_) => (),
+    }
+    Some(event)
+}
+
+/// The whole lines and columns to scroll by, keeping what's left of them in `rest`
+fn whole_scroll(scroll: &ScrollInput, rest: &mut (f64, f64)) -> (i64, i64) {
+    let lines = scroll.lines + rest.0;
+    let columns = scroll.columns + rest.1;
+    *rest = (lines.fract(), columns.fract());
+    (lines.trunc() as i64, columns.trunc() as i64)
+}
+
+async fn run(nvim: Neovim, timeout: Duration, mut receiver: UnboundedReceiver<InputEvent>) {
+    let mut queue = VecDeque::new();
+    let mut scroll_rest = (0.0, 0.0);
+
+    loop {
+        if queue.is_empty() {
+            match receiver.recv().await {
+                Some(event) => queue.push_back(event),
+                None => return,
+            }
+        }
+        while let Ok(event) = receiver.try_recv() {
+            queue.push_back(event);
+        }
+
+        if let Some(event) = next_batch(&mut queue) {
+            send(&nvim, timeout, event, &mut scroll_rest).await;
+        }
+    }
+}
+
+async fn send(nvim: &Neovim, timeout: Duration, event: InputEvent, scroll_rest: &mut (f64, f64)) {
+    let res = match event {
+        InputEvent::Keys(ref keys) => {
+            debug!("nvim_input -> {keys}");
+            call_timeout(timeout, nvim.input(keys))
+                .await
+                .map(|written| {
+                    if written < keys.len() as i64 {
+                        warn!("Neovim's input buffer is full, dropped some of {keys:?}");
+                    }
+                })
+        }
+        InputEvent::Mouse(ref mouse) => {
+            call_timeout(
+                timeout,
+                nvim.input_mouse(
+                    mouse.button,
+                    mouse.action,
+                    &mouse.modifier,
+                    mouse.grid,
+                    mouse.row,
+                    mouse.col,
+                ),
+            )
+            .await
+        }
+        InputEvent::Scroll(ref scroll) => {
+            let (lines, columns) = whole_scroll(scroll, scroll_rest);
+            if lines == 0 && columns == 0 {
+                return;
+            }
+            let args = vec![
+                Value::from(scroll.row),
+                Value::from(scroll.col),
+                Value::from(lines),
+                Value::from(columns),
+            ];
+            call_timeout(timeout, nvim.exec_lua(SCROLL_LUA, args))
+                .await
+                .map(|_| ())
+        }
+    };
+
+    // Neovim being slow to respond isn't fatal here, the input will still arrive at some point
+    match res {
+        Ok(()) => (),
+        Err(SessionError::CallError(err)) => err.print(),
+        Err(SessionError::TimeoutError(_)) => error!("Timed out sending {event:?} to Neovim"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mouse(action: &'static str) -> InputEvent {
+        InputEvent::Mouse(MouseInput {
+            button: "left",
+            action,
+            modifier: String::new(),
+            grid: 1,
+            row: 0,
+            col: 0,
+        })
+    }
+
+    #[test]
+    fn test_next_batch() {
+        let mut queue = VecDeque::from([
+            InputEvent::Keys("a".to_owned()),
+            InputEvent::Keys("<C-w>".to_owned()),
+            mouse("press"),
+            mouse("release"),
+            InputEvent::Keys("b".to_owned()),
+            InputEvent::Keys("c".to_owned()),
+        ]);
+
+        assert_eq!(
+            Some(InputEvent::Keys("a<C-w>".to_owned())),
+            next_batch(&mut queue)
+        );
+        assert_eq!(Some(mouse("press")), next_batch(&mut queue));
+        assert_eq!(Some(mouse("release")), next_batch(&mut queue));
+        assert_eq!(
+            Some(InputEvent::Keys("bc".to_owned())),
+            next_batch(&mut queue)
+        );
+        assert_eq!(None, next_batch(&mut queue));
+    }
+
+    fn scroll(lines: f64) -> InputEvent {
+        InputEvent::Scroll(ScrollInput {
+            row: 2,
+            col: 3,
+            lines,
+            columns: 0.0,
+        })
+    }
+
+    #[test]
+    fn test_next_batch_scroll() {
+        let mut queue = VecDeque::from([
+            scroll(0.5),
+            scroll(0.75),
+            InputEvent::Keys("j".to_owned()),
+            scroll(-0.25),
+        ]);
+
+        assert_eq!(Some(scroll(1.25)), next_batch(&mut queue));
+        assert_eq!(
+            Some(InputEvent::Keys("j".to_owned())),
+            next_batch(&mut queue)
+        );
+        assert_eq!(Some(scroll(-0.25)), next_batch(&mut queue));
+    }
+
+    #[test]
+    fn test_whole_scroll() {
+        let scroll = |lines, columns| ScrollInput {
+            row: 0,
+            col: 0,
+            lines,
+            columns,
+        };
+        let mut rest = (0.0, 0.0);
+
+        assert_eq!((0, 0), whole_scroll(&scroll(0.75, 0.0), &mut rest));
+        assert_eq!((1, 0), whole_scroll(&scroll(0.5, 0.0), &mut rest));
+        assert_eq!((0.25, 0.0), rest);
+        assert_eq!((-1, -2), whole_scroll(&scroll(-1.5, -2.0), &mut rest));
+        assert_eq!((-0.25, 0.0), rest);
+    }
+}
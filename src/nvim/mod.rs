@@ -1,12 +1,16 @@
 mod client;
 mod ext;
 mod handler;
+mod input_queue;
 mod redraw_handler;
 
 pub use self::client::{NeovimApiInfo, NeovimClient};
 pub use self::ext::*;
 pub use self::handler::NvimHandler;
-pub use self::redraw_handler::{NvimCommand, PendingPopupMenu, PopupMenuItem, RedrawMode};
+pub use self::input_queue::{MouseInput, ScrollInput};
+pub use self::redraw_handler::{
+    GridLineCell, NvimCommand, PendingPopupMenu, PopupMenuItem, RedrawMode,
+};
 
 use super::shell::ResizeState;
 
@@ -41,6 +45,7 @@ use nvim_rs::{
     error::{CallError, DecodeError, LoopError},
 };
 
+use self::input_queue::InputQueue;
 use crate::nvim_config::NvimConfig;
 
 #[derive(Debug)]
@@ -268,11 +273,37 @@ pub struct NvimSession {
     nvim: Neovim,
     timeout: Duration,
     runtime: Arc<Runtime>,
+    input: InputQueue,
 }
 
 type IoFuture<'a> = BoxFuture<'a, Result<(), Box<LoopError>>>;
 
+/// Wrap a future from an RPC call to neovim within a timeout
+async fn call_timeout<F, T>(duration: Duration, f: F) -> Result<T, SessionError>
+where
+    F: Future<Output = Result<T, Box<CallError>>>,
+{
+    match timeout(duration, f).await {
+        Ok(f) => match f {
+            Ok(f) => Ok(f),
+            Err(e) => Err(e.into()),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
 impl NvimSession {
+    fn new(nvim: Neovim, timeout: Duration, runtime: Arc<Runtime>) -> Self {
+        let input = InputQueue::new(nvim.clone(), timeout, &runtime);
+
+        Self {
+            nvim,
+            timeout,
+            runtime,
+            input,
+        }
+    }
+
     pub fn new_child<'a>(
         mut cmd: Command,
         handler: NvimHandler,
@@ -295,14 +326,7 @@ impl NvimSession {
             handler,
         );
 
-        Ok((
-            Self {
-                nvim,
-                timeout,
-                runtime,
-            },
-            io_future.boxed(),
-        ))
+        Ok((Self::new(nvim, timeout, runtime), io_future.boxed()))
     }
 
     pub fn new_tcp_client<'a>(
@@ -325,14 +349,7 @@ impl NvimSession {
             handler,
         );
 
-        Ok((
-            Self {
-                nvim,
-                timeout,
-                runtime,
-            },
-            io_future.boxed(),
-        ))
+        Ok((Self::new(nvim, timeout, runtime), io_future.boxed()))
     }
 
     #[cfg(unix)]
@@ -357,14 +374,7 @@ impl NvimSession {
             handler,
         );
 
-        Ok((
-            Self {
-                nvim,
-                timeout,
-                runtime,
-            },
-            io_future.boxed(),
-        ))
+        Ok((Self::new(nvim, timeout, runtime), io_future.boxed()))
     }
 
     /// Wrap a future from an RPC call to neovim within a timeout
@@ -372,13 +382,23 @@ impl NvimSession {
     where
         F: Future<Output = Result<T, Box<CallError>>>,
     {
-        match timeout(self.timeout, f).await {
-            Ok(f) => match f {
-                Ok(f) => Ok(f),
-                Err(e) => Err(e.into()),
-            },
-            Err(e) => Err(e.into()),
-        }
+        call_timeout(self.timeout, f).await
+    }
+
+    /// Queue keys for `nvim_input()`, without waiting for Neovim to receive them
+    pub fn queue_input(&self, keys: impl Into<String>) {
+        self.input.keys(keys.into());
+    }
+
+    /// Queue a mouse event for `nvim_input_mouse()`, keeping its order with queued keys
+    pub fn queue_mouse(&self, mouse: MouseInput) {
+        self.input.mouse(mouse);
+    }
+
+    /// Queue a precise scroll of the window under a screen cell, keeping its order with queued
+    /// keys and mouse events
+    pub fn queue_scroll(&self, scroll: ScrollInput) {
+        self.input.scroll(scroll);
     }
 
     /// Execute a future on the current thread using this session's tokio runtime
@@ -617,7 +637,9 @@ pub async fn post_start_init(
                     .set_tabline_external(api_info.ext_tabline)
                     .set_linegrid_external(true)
                     .set_hlstate_external(api_info.ext_hlstate)
-                    .set_termcolors_external(api_info.ext_termcolors),
+                    .set_termcolors_external(api_info.ext_termcolors)
+                    .set_multigrid_external(api_info.ext_multigrid)
+                    .set_messages_externa(api_info.ext_messages),
             ),
         )
         .await
@@ -1,9 +1,12 @@
-use std::{convert::*, mem, num::ParseFloatError, result, sync::Arc};
+use std::{collections::HashMap, convert::*, mem, num::ParseFloatError, result, sync::Arc};
 
 use nvim_rs::Value;
 
 use log::{debug, error, warn};
 
+use crate::cursor::CursorAnimation;
+use crate::grid::GridViewport;
+use crate::messages::{Message, MessageChunk};
 use crate::nvim::{NvimSession, Tabpage};
 use crate::shell;
 use crate::ui::UiMutex;
@@ -93,6 +96,15 @@ macro_rules! try_option_u32 {
     };
 }
 
+macro_rules! try_float {
+    ($exp:expr) => {{
+        let val = $exp;
+        val.as_f64()
+            .or_else(|| val.as_i64().map(|i| i as f64))
+            .ok_or_else(|| "Can't convert argument to float".to_owned())?
+    }};
+}
+
 macro_rules! try_bool {
     ($exp:expr) => {
         $exp.as_bool()
@@ -211,6 +223,10 @@ pub enum NvimCommand {
     ShowGtkInspector,
     Transparency(f64, f64),
     PreferDarkTheme(bool),
+    Menubar(bool),
+    Toolbar(bool),
+    RefreshMenus,
+    SetTitle(String),
 }
 
 pub fn call_gui_event(
@@ -264,9 +280,15 @@ pub fn call_gui_event(
                     )?;
                     ui.set_tabline(opt_value);
                 }
-                "Cmdline" => set_ui_opt(
-                    &nvim,
-                    &[
+                "StatusStrip" => ui.set_status_strip(opt_value),
+                "SmoothScroll" => ui.set_smooth_scroll(opt_value),
+                "ContextMenu" => ui.set_context_menu(opt_value),
+                "KeycodeFallback" => ui.set_keycode_fallback(opt_value),
+                "Menubar" => ui.on_command(NvimCommand::Menubar(opt_value)),
+                "Toolbar" => ui.on_command(NvimCommand::Toolbar(opt_value)),
+                "Langmap" => ui.set_langmap(opt_value),
+                "Cmdline" => {
+                    let cmdline_opts = [
                         (
                             "ext_cmdline",
                             api_info
@@ -281,9 +303,24 @@ pub fn call_gui_event(
                                 .map(|api_info| api_info.ext_wildmenu)
                                 .unwrap_or_default(),
                         ),
-                    ],
-                    opt_value,
-                )?,
+                    ];
+                    let ext_messages = api_info
+                        .as_ref()
+                        .is_some_and(|api_info| api_info.ext_messages);
+
+                    // Neovim keeps the command line external for as long as messages are, so
+                    // messages go back to the message grid along with it
+                    if opt_value {
+                        set_ui_opt(&nvim, &cmdline_opts, true)?;
+                    }
+                    if ext_messages {
+                        set_ui_opt(&nvim, &[("ext_messages", true)], opt_value)?;
+                        ui.set_messages_external(opt_value);
+                    }
+                    if !opt_value {
+                        set_ui_opt(&nvim, &cmdline_opts, false)?;
+                    }
+                }
                 opt => error!("Unknown option {opt}"),
             }
         }
@@ -292,6 +329,7 @@ pub fn call_gui_event(
                 "ToggleSidebar" => ui.on_command(NvimCommand::ToggleSidebar),
                 "ShowProjectView" => ui.on_command(NvimCommand::ShowProjectView),
                 "ShowGtkInspector" => ui.on_command(NvimCommand::ShowGtkInspector),
+                "MenuRefresh" => ui.on_command(NvimCommand::RefreshMenus),
                 "Transparency" => ui.on_command(NvimCommand::Transparency(
                     try_str!(args.get(1).cloned().unwrap_or_else(|| "1.0".into()))
                         .parse()
@@ -308,6 +346,33 @@ pub fn call_gui_event(
 
                     ui.on_command(NvimCommand::PreferDarkTheme(prefer_dark_theme))
                 }
+                "FloatStyle" => {
+                    let shadow = matches!(
+                        try_str!(args.get(1).cloned().unwrap_or_else(|| Value::from("on"))),
+                        "on"
+                    );
+                    let corner_radius =
+                        try_str!(args.get(2).cloned().unwrap_or_else(|| Value::from("6")))
+                            .parse::<f32>()
+                            .map_err(|e| e.to_string())?;
+
+                    ui.set_float_style(shell::FloatStyle {
+                        shadow,
+                        corner_radius: corner_radius.max(0.0),
+                    });
+                }
+                "CursorAnimation" => {
+                    let enabled = matches!(
+                        try_str!(args.get(1).cloned().unwrap_or_else(|| Value::from("on"))),
+                        "on"
+                    );
+                    let trail = args
+                        .get(2)
+                        .and_then(Value::as_str)
+                        .is_some_and(|arg| arg == "trail");
+
+                    ui.set_cursor_animation(CursorAnimation { enabled, trail });
+                }
                 "SetCursorBlink" => {
                     let blink_count =
                         try_str!(args.get(1).cloned().unwrap_or_else(|| Value::from(-1)))
@@ -372,118 +437,620 @@ pub fn call_gui_request(
     }
 }
 
-pub fn call(
-    ui: &mut shell::State,
-    method: &str,
-    args: Vec<Value>,
-) -> result::Result<(RedrawMode, PendingPopupMenu), String> {
-    let mut flush = false;
-    let repaint_mode = match method {
-        "grid_line" => call!(ui->grid_line(args: uint, uint, uint, ext)),
-        "grid_clear" => call!(ui->grid_clear(args: uint)),
-        "grid_destroy" => call!(ui->grid_destroy(args: uint)),
-        "grid_cursor_goto" => call!(ui->grid_cursor_goto(args: uint, uint, uint)),
-        "grid_scroll" => call!(ui->grid_scroll(args: uint, uint, uint, uint, uint, int, int)),
-        "grid_resize" => call!(ui->grid_resize(args: uint, uint, uint)),
-        "default_colors_set" => {
-            call!(ui->default_colors_set(args: opt_uint, opt_uint, opt_uint, int, int))
-        }
-        "hl_attr_define" => call!(ui->hl_attr_define(args: uint, ext, val_ref, ext)),
-        "mode_change" => call!(ui->on_mode_change(args: str, uint)),
-        "mouse_on" => ui.on_mouse(true),
-        "mouse_off" => ui.on_mouse(false),
-        "busy_start" => ui.on_busy(true),
-        "busy_stop" => ui.on_busy(false),
-        "popupmenu_show" => {
-            /* Complete lists can be enormous, so we want to be careful to avoid duplicating strings
-             * by consuming the argument list */
-            let mut iter = args.into_iter();
-            #[rustfmt::skip]
-            let menu_items_in = into_array!(
-                iter.next().ok_or("Menu list array is missing")?,
-                "Failed to get menu list array",
-                |item| into_array!(
-                    item,
-                    "Failed to get menu item array",
-                    |col| try_string!(col)
-                )
-            )?;
-
-            // XXX: Use try_collect() when that stabilizes
-            let mut menu_items = Vec::with_capacity(menu_items_in.len());
-            for menu_item in menu_items_in.into_iter() {
-                menu_items.push(PopupMenuItem::new(menu_item)?);
+/// A run of cells from a `grid_line` event. Neovim leaves out the highlight of cells that use
+/// the same one as the cell before them, which gets resolved while decoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GridLineCell {
+    pub text: String,
+    pub hl_id: Option<u64>,
+    pub repeat: usize,
+}
+
+/// A redraw event from Neovim, decoded and validated on the RPC thread so that the UI thread only
+/// has to apply it
+#[derive(Debug, PartialEq)]
+pub enum RedrawEvent {
+    GridLine {
+        grid: u64,
+        row: u64,
+        col_start: u64,
+        cells: Vec<GridLineCell>,
+    },
+    GridClear(u64),
+    GridDestroy(u64),
+    GridCursorGoto {
+        grid: u64,
+        row: u64,
+        col: u64,
+    },
+    GridScroll {
+        grid: u64,
+        top: u64,
+        bot: u64,
+        left: u64,
+        right: u64,
+        rows: i64,
+        cols: i64,
+    },
+    GridResize {
+        grid: u64,
+        width: u64,
+        height: u64,
+    },
+    WinPos {
+        grid: u64,
+        win: Value,
+        start_row: u64,
+        start_col: u64,
+        width: u64,
+        height: u64,
+    },
+    WinFloatPos {
+        grid: u64,
+        win: Value,
+        anchor: String,
+        anchor_grid: u64,
+        anchor_row: f64,
+        anchor_col: f64,
+        focusable: bool,
+        zindex: u64,
+    },
+    WinHide(u64),
+    WinClose(u64),
+    MsgSetPos {
+        grid: u64,
+        row: u64,
+        zindex: Option<u64>,
+    },
+    WinViewport {
+        grid: u64,
+        viewport: GridViewport,
+    },
+    DefaultColorsSet {
+        fg: Option<u64>,
+        bg: Option<u64>,
+        sp: Option<u64>,
+        cterm_fg: i64,
+        cterm_bg: i64,
+    },
+    HlAttrDefine {
+        id: u64,
+        rgb_attr: HashMap<String, Value>,
+        info: Vec<HashMap<String, Value>>,
+    },
+    ModeChange {
+        mode: String,
+        idx: u64,
+    },
+    Mouse(bool),
+    Busy(bool),
+    Bell {
+        visual: bool,
+    },
+    SetTitle(String),
+    PopupMenu(PendingPopupMenu),
+    TablineUpdate {
+        current: Value,
+        tabs: Vec<(Value, Option<String>)>,
+    },
+    ModeInfoSet {
+        cursor_style_enabled: bool,
+        mode_info: Vec<HashMap<String, Value>>,
+    },
+    OptionSet {
+        name: String,
+        value: Value,
+    },
+    CmdlineShow {
+        content: Vec<(u64, String)>,
+        pos: u64,
+        firstc: String,
+        prompt: String,
+        indent: u64,
+        level: u64,
+    },
+    CmdlineBlockShow(Vec<Vec<(u64, String)>>),
+    CmdlineBlockAppend(Vec<(u64, String)>),
+    CmdlineHide(u64),
+    CmdlineBlockHide,
+    CmdlinePos {
+        pos: u64,
+        level: u64,
+    },
+    CmdlineSpecialChar {
+        c: String,
+        shift: bool,
+        level: u64,
+    },
+    WildmenuShow(Vec<String>),
+    WildmenuHide,
+    WildmenuSelect(i64),
+    MsgShow {
+        message: Message,
+        replace_last: bool,
+    },
+    MsgClear,
+    MsgShowmode(Message),
+    MsgShowcmd(Message),
+    MsgRuler(Message),
+    MsgHistoryShow(Vec<Message>),
+    MsgHistoryClear,
+    Flush,
+}
+
+macro_rules! next_arg {
+    ($iter:ident, $name:literal) => {
+        $iter
+            .next()
+            .ok_or_else(|| format!("{} is missing", $name))?
+    };
+}
+
+/// Decode the cells of a `grid_line` event, resolving the highlight of each run
+fn grid_line_cells(cells: Value) -> Result<Vec<GridLineCell>, String> {
+    let mut hl_id = None;
+
+    into_array!(cells, "grid_line cells is not an array", |cell| {
+        let Value::Array(cell) = cell else {
+            return Err("grid_line cell is not an array".to_owned());
+        };
+        let mut cell = cell.into_iter();
+        let text = try_string!(next_arg!(cell, "grid_line cell text"))?;
+        hl_id = cell.next().and_then(|hl| hl.as_u64()).or(hl_id);
+        let repeat = cell.next().and_then(|repeat| repeat.as_u64()).unwrap_or(1) as usize;
+
+        Ok::<_, String>(GridLineCell {
+            text,
+            hl_id,
+            repeat,
+        })
+    })
+}
+
+impl RedrawEvent {
+    /// Decode a single call of the redraw event `method`. Returns `None` for events we don't
+    /// handle.
+    pub fn decode(method: &str, args: Vec<Value>) -> result::Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        let event = match method {
+            "grid_line" => RedrawEvent::GridLine {
+                grid: try_uint!(next_arg!(args, "Grid")),
+                row: try_uint!(next_arg!(args, "Row")),
+                col_start: try_uint!(next_arg!(args, "Start column")),
+                cells: grid_line_cells(next_arg!(args, "Cells"))?,
+            },
+            "grid_clear" => RedrawEvent::GridClear(try_uint!(next_arg!(args, "Grid"))),
+            "grid_destroy" => RedrawEvent::GridDestroy(try_uint!(next_arg!(args, "Grid"))),
+            "grid_cursor_goto" => RedrawEvent::GridCursorGoto {
+                grid: try_uint!(next_arg!(args, "Grid")),
+                row: try_uint!(next_arg!(args, "Row")),
+                col: try_uint!(next_arg!(args, "Column")),
+            },
+            "grid_scroll" => RedrawEvent::GridScroll {
+                grid: try_uint!(next_arg!(args, "Grid")),
+                top: try_uint!(next_arg!(args, "Top")),
+                bot: try_uint!(next_arg!(args, "Bottom")),
+                left: try_uint!(next_arg!(args, "Left")),
+                right: try_uint!(next_arg!(args, "Right")),
+                rows: try_int!(next_arg!(args, "Rows")),
+                cols: try_int!(next_arg!(args, "Columns")),
+            },
+            "grid_resize" => RedrawEvent::GridResize {
+                grid: try_uint!(next_arg!(args, "Grid")),
+                width: try_uint!(next_arg!(args, "Width")),
+                height: try_uint!(next_arg!(args, "Height")),
+            },
+            "win_pos" => RedrawEvent::WinPos {
+                grid: try_uint!(next_arg!(args, "Grid")),
+                win: next_arg!(args, "Window"),
+                start_row: try_uint!(next_arg!(args, "Start row")),
+                start_col: try_uint!(next_arg!(args, "Start column")),
+                width: try_uint!(next_arg!(args, "Width")),
+                height: try_uint!(next_arg!(args, "Height")),
+            },
+            "win_float_pos" => RedrawEvent::WinFloatPos {
+                grid: try_uint!(next_arg!(args, "Grid")),
+                win: next_arg!(args, "Window"),
+                anchor: try_string!(next_arg!(args, "Anchor"))?,
+                anchor_grid: try_uint!(next_arg!(args, "Anchor grid")),
+                anchor_row: try_float!(next_arg!(args, "Anchor row")),
+                anchor_col: try_float!(next_arg!(args, "Anchor column")),
+                focusable: try_bool!(next_arg!(args, "Focusable")),
+                zindex: try_uint!(next_arg!(args, "zindex")),
+            },
+            "win_hide" => RedrawEvent::WinHide(try_uint!(next_arg!(args, "Grid"))),
+            "win_close" => RedrawEvent::WinClose(try_uint!(next_arg!(args, "Grid"))),
+            "msg_set_pos" => {
+                let grid = try_uint!(next_arg!(args, "Message grid"));
+                let row = try_uint!(next_arg!(args, "Message row"));
+                // zindex was only added in Neovim 0.10
+                let zindex = args.nth(2).as_ref().and_then(Value::as_u64);
+                RedrawEvent::MsgSetPos { grid, row, zindex }
+            }
+            "win_viewport" => {
+                let grid = try_uint!(next_arg!(args, "win_viewport grid"));
+                next_arg!(args, "win_viewport win");
+                RedrawEvent::WinViewport {
+                    grid,
+                    viewport: GridViewport {
+                        topline: try_int!(next_arg!(args, "win_viewport topline")),
+                        botline: try_int!(next_arg!(args, "win_viewport botline")),
+                        curline: try_int!(next_arg!(args, "win_viewport curline")),
+                        curcol: try_int!(next_arg!(args, "win_viewport curcol")),
+                        line_count: try_int!(next_arg!(args, "win_viewport line_count")),
+                        // scroll_delta was only added in Neovim 0.10
+                        scroll_delta: args.next().and_then(|val| val.as_i64()).unwrap_or(0),
+                    },
+                }
+            }
+            "default_colors_set" => RedrawEvent::DefaultColorsSet {
+                fg: try_option_uint!(next_arg!(args, "Foreground")),
+                bg: try_option_uint!(next_arg!(args, "Background")),
+                sp: try_option_uint!(next_arg!(args, "Special")),
+                cterm_fg: try_int!(next_arg!(args, "cterm foreground")),
+                cterm_bg: try_int!(next_arg!(args, "cterm background")),
+            },
+            "hl_attr_define" => {
+                let id = try_uint!(next_arg!(args, "Highlight id"));
+                let rgb_attr = try_arg!(next_arg!(args, "RGB attributes"), ext);
+                next_arg!(args, "cterm attributes");
+                let info = try_arg!(next_arg!(args, "Highlight info"), ext);
+                RedrawEvent::HlAttrDefine { id, rgb_attr, info }
             }
+            "mode_change" => RedrawEvent::ModeChange {
+                mode: try_string!(next_arg!(args, "Mode"))?,
+                idx: try_uint!(next_arg!(args, "Mode index")),
+            },
+            "mouse_on" => RedrawEvent::Mouse(true),
+            "mouse_off" => RedrawEvent::Mouse(false),
+            "busy_start" => RedrawEvent::Busy(true),
+            "busy_stop" => RedrawEvent::Busy(false),
+            "bell" => RedrawEvent::Bell { visual: false },
+            "visual_bell" => RedrawEvent::Bell { visual: true },
+            "set_title" => RedrawEvent::SetTitle(try_string!(next_arg!(args, "Title"))?),
+            // GTK 4 has no separate title for iconified windows
+            "set_icon" => return Ok(None),
+            "popupmenu_show" => {
+                /* Complete lists can be enormous, so we want to be careful to avoid duplicating
+                 * strings by consuming the argument list */
+                #[rustfmt::skip]
+                let menu_items_in = into_array!(
+                    next_arg!(args, "Menu list array"),
+                    "Failed to get menu list array",
+                    |item| into_array!(
+                        item,
+                        "Failed to get menu item array",
+                        |col| try_string!(col)
+                    )
+                )?;
 
-            ui.set_pending_popupmenu(PendingPopupMenu::Show {
-                items: menu_items,
-                selected: try_option_u32!(
-                    iter.next().ok_or("Failed to get selected popupmenu row")?
-                ),
-                pos: (
-                    try_uint!(iter.next().ok_or("Failed to get popupmenu row")?),
-                    try_uint!(iter.next().ok_or("Failed to get popupmenu col")?),
-                ),
-            })
-        }
-        "popupmenu_hide" => ui.set_pending_popupmenu(PendingPopupMenu::Hide),
-        "popupmenu_select" => {
-            ui.set_pending_popupmenu(PendingPopupMenu::Select(try_option_u32!(&args[0])))
+                // XXX: Use try_collect() when that stabilizes
+                let mut menu_items = Vec::with_capacity(menu_items_in.len());
+                for menu_item in menu_items_in.into_iter() {
+                    menu_items.push(PopupMenuItem::new(menu_item)?);
+                }
+
+                RedrawEvent::PopupMenu(PendingPopupMenu::Show {
+                    items: menu_items,
+                    selected: try_option_u32!(next_arg!(args, "Selected popupmenu row")),
+                    pos: (
+                        try_uint!(next_arg!(args, "Popupmenu row")),
+                        try_uint!(next_arg!(args, "Popupmenu col")),
+                    ),
+                })
+            }
+            "popupmenu_hide" => RedrawEvent::PopupMenu(PendingPopupMenu::Hide),
+            "popupmenu_select" => RedrawEvent::PopupMenu(PendingPopupMenu::Select(
+                try_option_u32!(next_arg!(args, "Selected popupmenu row")),
+            )),
+            "tabline_update" => {
+                let current = next_arg!(args, "Current tab");
+                let tabs = map_array!(
+                    next_arg!(args, "Tab list"),
+                    "Error get tabline list".to_owned(),
+                    |tab| tab
+                        .as_map()
+                        .ok_or_else(|| "Error get map for tab".to_owned())
+                        .and_then(|tab_map| tab_map.to_attrs_map())
+                        .and_then(|tab_attrs| {
+                            let name = tab_attrs
+                                .get("name")
+                                .and_then(|n| n.as_str().map(|s| s.to_owned()));
+                            let tab = tab_attrs.get("tab").ok_or("Tab is missing its id")?;
+
+                            Ok(((*tab).clone(), name))
+                        })
+                )?;
+                RedrawEvent::TablineUpdate { current, tabs }
+            }
+            "mode_info_set" => RedrawEvent::ModeInfoSet {
+                cursor_style_enabled: try_bool!(next_arg!(args, "cursor_style_enabled")),
+                mode_info: try_arg!(next_arg!(args, "Mode info"), ext),
+            },
+            "option_set" => RedrawEvent::OptionSet {
+                name: try_string!(next_arg!(args, "Option name"))?,
+                value: next_arg!(args, "Option value"),
+            },
+            "cmdline_show" => RedrawEvent::CmdlineShow {
+                content: try_arg!(next_arg!(args, "Cmdline content"), ext),
+                pos: try_uint!(next_arg!(args, "Cmdline position")),
+                firstc: try_string!(next_arg!(args, "Cmdline firstc"))?,
+                prompt: try_string!(next_arg!(args, "Cmdline prompt"))?,
+                indent: try_uint!(next_arg!(args, "Cmdline indent")),
+                level: try_uint!(next_arg!(args, "Cmdline level")),
+            },
+            "cmdline_block_show" => {
+                RedrawEvent::CmdlineBlockShow(try_arg!(next_arg!(args, "Cmdline block"), ext))
+            }
+            "cmdline_block_append" => {
+                RedrawEvent::CmdlineBlockAppend(try_arg!(next_arg!(args, "Cmdline line"), ext))
+            }
+            "cmdline_hide" => RedrawEvent::CmdlineHide(try_uint!(next_arg!(args, "Cmdline level"))),
+            "cmdline_block_hide" => RedrawEvent::CmdlineBlockHide,
+            "cmdline_pos" => RedrawEvent::CmdlinePos {
+                pos: try_uint!(next_arg!(args, "Cmdline position")),
+                level: try_uint!(next_arg!(args, "Cmdline level")),
+            },
+            "cmdline_special_char" => RedrawEvent::CmdlineSpecialChar {
+                c: try_string!(next_arg!(args, "Special char"))?,
+                shift: try_bool!(next_arg!(args, "Special char shift")),
+                level: try_uint!(next_arg!(args, "Cmdline level")),
+            },
+            "wildmenu_show" => {
+                RedrawEvent::WildmenuShow(try_arg!(next_arg!(args, "Wildmenu items"), ext))
+            }
+            "wildmenu_hide" => RedrawEvent::WildmenuHide,
+            "wildmenu_select" => {
+                RedrawEvent::WildmenuSelect(try_int!(next_arg!(args, "Wildmenu selection")))
+            }
+            "msg_show" => {
+                let kind = try_string!(next_arg!(args, "Message kind"))?;
+                let content = message_content(&next_arg!(args, "Message content"))?;
+                let replace_last = try_bool!(next_arg!(args, "Message replace_last"));
+                RedrawEvent::MsgShow {
+                    message: Message::new(kind, content),
+                    replace_last,
+                }
+            }
+            "msg_clear" => RedrawEvent::MsgClear,
+            "msg_showmode" | "msg_showcmd" | "msg_ruler" => {
+                let content = message_content(&next_arg!(args, "Message content"))?;
+                let content = Message::new(String::new(), content);
+                match method {
+                    "msg_showmode" => RedrawEvent::MsgShowmode(content),
+                    "msg_showcmd" => RedrawEvent::MsgShowcmd(content),
+                    _ => RedrawEvent::MsgRuler(content),
+                }
+            }
+            "msg_history_show" => {
+                let entries = map_array!(
+                    next_arg!(args, "Message history"),
+                    "Message history is not an array".to_owned(),
+                    |entry| {
+                        let entry = entry
+                            .as_array()
+                            .ok_or("Message history entry is not an array")?;
+                        let kind = try_str!(entry.first().ok_or("Message kind is missing")?);
+                        let content =
+                            message_content(entry.get(1).ok_or("Message content is missing")?)?;
+                        Ok(Message::new(kind.to_owned(), content))
+                    }
+                )?;
+                RedrawEvent::MsgHistoryShow(entries)
+            }
+            "msg_history_clear" => RedrawEvent::MsgHistoryClear,
+            "flush" => RedrawEvent::Flush,
+            _ => {
+                warn!("Event {method}({:?})", args.as_slice());
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(event))
+    }
+}
+
+/// Decode a batch of redraw events, as sent in a single `redraw` notification. Events that fail to
+/// decode are logged and skipped.
+pub fn decode_redraw(params: Vec<Value>) -> Vec<RedrawEvent> {
+    let mut events = Vec::new();
+
+    for ev in params {
+        let ev_args = match ev {
+            Value::Array(args) => args,
+            _ => {
+                error!("Unsupported event type: {ev:?}");
+                continue;
+            }
+        };
+        let mut args_iter = ev_args.into_iter();
+        let ev_name = match args_iter.next() {
+            Some(ev_name) => ev_name,
+            None => {
+                error!(
+                    "No name provided with redraw event, args: {:?}",
+                    args_iter.as_slice()
+                );
+                continue;
+            }
+        };
+        let ev_name = match ev_name.as_str() {
+            Some(ev_name) => ev_name,
+            None => {
+                error!(
+                    "Expected event name to be str, instead got {:?}. Args: {:?}",
+                    ev_name,
+                    args_iter.as_slice()
+                );
+                continue;
+            }
+        };
+
+        for local_args in args_iter {
+            let args = match local_args {
+                Value::Array(ar) => ar,
+                _ => vec![],
+            };
+
+            match RedrawEvent::decode(ev_name, args) {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => (),
+                Err(desc) => error!("Event {ev_name}\n{desc}"),
+            }
         }
-        "tabline_update" => {
-            if let Some(nvim) = ui.nvim() {
-                let tabs_out = map_array!(args[1], "Error get tabline list".to_owned(), |tab| tab
-                    .as_map()
-                    .ok_or_else(|| "Error get map for tab".to_owned())
-                    .and_then(|tab_map| tab_map.to_attrs_map())
-                    .map(|tab_attrs| {
-                        let name_attr = tab_attrs
-                            .get("name")
-                            .and_then(|n| n.as_str().map(|s| s.to_owned()));
-                        let tab_attr = tab_attrs
-                            .get("tab")
-                            .map(|&tab_id| Tabpage::new(tab_id.clone(), (*nvim).clone()))
-                            .unwrap();
-
-                        (tab_attr, name_attr)
-                    }))?;
-                ui.tabline_update(Tabpage::new(args[0].clone(), (*nvim).clone()), tabs_out)
-            } else {
-                RedrawMode::Nothing
+    }
+
+    events
+}
+
+/// Apply a decoded redraw event. Returns the redraw needed and the popup menu changes once the
+/// event is a flush, so that nothing gets drawn in the middle of an update.
+pub fn apply(ui: &mut shell::State, event: RedrawEvent) -> (RedrawMode, PendingPopupMenu) {
+    let mut flush = false;
+    let repaint_mode = match event {
+        RedrawEvent::GridLine {
+            grid,
+            row,
+            col_start,
+            cells,
+        } => ui.grid_line(grid, row, col_start, cells),
+        RedrawEvent::GridClear(grid) => ui.grid_clear(grid),
+        RedrawEvent::GridDestroy(grid) => ui.grid_destroy(grid),
+        RedrawEvent::GridCursorGoto { grid, row, col } => ui.grid_cursor_goto(grid, row, col),
+        RedrawEvent::GridScroll {
+            grid,
+            top,
+            bot,
+            left,
+            right,
+            rows,
+            cols,
+        } => ui.grid_scroll(grid, top, bot, left, right, rows, cols),
+        RedrawEvent::GridResize {
+            grid,
+            width,
+            height,
+        } => ui.grid_resize(grid, width, height),
+        RedrawEvent::WinPos {
+            grid,
+            win,
+            start_row,
+            start_col,
+            width,
+            height,
+        } => ui.win_pos(grid, win, start_row, start_col, width, height),
+        RedrawEvent::WinFloatPos {
+            grid,
+            win,
+            anchor,
+            anchor_grid,
+            anchor_row,
+            anchor_col,
+            focusable,
+            zindex,
+        } => ui.win_float_pos(
+            grid,
+            win,
+            anchor,
+            anchor_grid,
+            anchor_row,
+            anchor_col,
+            focusable,
+            zindex,
+        ),
+        RedrawEvent::WinHide(grid) => ui.win_hide(grid),
+        RedrawEvent::WinClose(grid) => ui.win_close(grid),
+        RedrawEvent::MsgSetPos { grid, row, zindex } => ui.msg_set_pos(grid, row, zindex),
+        RedrawEvent::WinViewport { grid, viewport } => ui.win_viewport(grid, viewport),
+        RedrawEvent::DefaultColorsSet {
+            fg,
+            bg,
+            sp,
+            cterm_fg,
+            cterm_bg,
+        } => ui.default_colors_set(fg, bg, sp, cterm_fg, cterm_bg),
+        RedrawEvent::HlAttrDefine { id, rgb_attr, info } => ui.hl_attr_define(id, rgb_attr, info),
+        RedrawEvent::ModeChange { mode, idx } => ui.on_mode_change(mode, idx),
+        RedrawEvent::Mouse(on) => ui.on_mouse(on),
+        RedrawEvent::Busy(busy) => ui.on_busy(busy),
+        RedrawEvent::Bell { visual } => ui.on_bell(visual),
+        RedrawEvent::SetTitle(title) => ui.set_title(title),
+        RedrawEvent::PopupMenu(popupmenu) => ui.set_pending_popupmenu(popupmenu),
+        RedrawEvent::TablineUpdate { current, tabs } => match ui.nvim() {
+            Some(nvim) => {
+                let tabs = tabs
+                    .into_iter()
+                    .map(|(tab, name)| (Tabpage::new(tab, (*nvim).clone()), name))
+                    .collect();
+                ui.tabline_update(Tabpage::new(current, (*nvim).clone()), tabs)
             }
+            None => RedrawMode::Nothing,
+        },
+        RedrawEvent::ModeInfoSet {
+            cursor_style_enabled,
+            mode_info,
+        } => ui.mode_info_set(cursor_style_enabled, mode_info),
+        RedrawEvent::OptionSet { name, value } => ui.option_set(name, value),
+        RedrawEvent::CmdlineShow {
+            content,
+            pos,
+            firstc,
+            prompt,
+            indent,
+            level,
+        } => ui.cmdline_show(content, pos, firstc, prompt, indent, level),
+        RedrawEvent::CmdlineBlockShow(content) => ui.cmdline_block_show(content),
+        RedrawEvent::CmdlineBlockAppend(content) => ui.cmdline_block_append(content),
+        RedrawEvent::CmdlineHide(level) => ui.cmdline_hide(level),
+        RedrawEvent::CmdlineBlockHide => ui.cmdline_block_hide(),
+        RedrawEvent::CmdlinePos { pos, level } => ui.cmdline_pos(pos, level),
+        RedrawEvent::CmdlineSpecialChar { c, shift, level } => {
+            ui.cmdline_special_char(c, shift, level)
         }
-        "mode_info_set" => call!(ui->mode_info_set(args: bool, ext)),
-        "option_set" => call!(ui->option_set(args: str, val)),
-        "cmdline_show" => call!(ui->cmdline_show(args: ext, uint, str, str, uint, uint)),
-        "cmdline_block_show" => call!(ui->cmdline_block_show(args: ext)),
-        "cmdline_block_append" => call!(ui->cmdline_block_append(args: ext)),
-        "cmdline_hide" => call!(ui->cmdline_hide(args: uint)),
-        "cmdline_block_hide" => ui.cmdline_block_hide(),
-        "cmdline_pos" => call!(ui->cmdline_pos(args: uint, uint)),
-        "cmdline_special_char" => call!(ui->cmdline_special_char(args: str, bool, uint)),
-        "wildmenu_show" => call!(ui->wildmenu_show(args: ext)),
-        "wildmenu_hide" => ui.wildmenu_hide(),
-        "wildmenu_select" => call!(ui->wildmenu_select(args: int)),
-        "flush" => {
+        RedrawEvent::WildmenuShow(items) => ui.wildmenu_show(items),
+        RedrawEvent::WildmenuHide => ui.wildmenu_hide(),
+        RedrawEvent::WildmenuSelect(selected) => ui.wildmenu_select(selected),
+        RedrawEvent::MsgShow {
+            message,
+            replace_last,
+        } => ui.msg_show(message, replace_last),
+        RedrawEvent::MsgClear => ui.msg_clear(),
+        RedrawEvent::MsgShowmode(content) => ui.msg_showmode(content),
+        RedrawEvent::MsgShowcmd(content) => ui.msg_showcmd(content),
+        RedrawEvent::MsgRuler(content) => ui.msg_ruler(content),
+        RedrawEvent::MsgHistoryShow(entries) => ui.msg_history_show(entries),
+        RedrawEvent::MsgHistoryClear => ui.msg_history_clear(),
+        RedrawEvent::Flush => {
             debug!("Flush ({:?})", ui.pending_redraw);
             flush = true;
             ui.pending_redraw
         }
-        _ => {
-            warn!("Event {method}({args:?})");
-            RedrawMode::Nothing
-        }
     };
 
     if flush {
         ui.pending_redraw = RedrawMode::Nothing;
-        Ok((repaint_mode, ui.pending_popupmenu.take()))
+        (repaint_mode, ui.pending_popupmenu.take())
     } else {
         ui.pending_redraw = ui.pending_redraw.max(repaint_mode);
-        Ok((RedrawMode::Nothing, PendingPopupMenu::None))
+        (RedrawMode::Nothing, PendingPopupMenu::None)
     }
 }
 
+/// Parse the content of a message, as a list of [attr_id, text] chunks
+fn message_content(content: &Value) -> Result<Vec<MessageChunk>, String> {
+    map_array!(
+        content,
+        "Message content is not an array".to_owned(),
+        |chunk| {
+            let chunk = chunk.as_array().ok_or("Message chunk is not an array")?;
+            Ok(MessageChunk {
+                hl_id: try_uint!(chunk.first().ok_or("Message chunk attr_id is missing")?),
+                text: try_str!(chunk.get(1).ok_or("Message chunk text is missing")?).to_owned(),
+            })
+        }
+    )
+}
+
 /// Represents the next pending popup menu action before we've actually performed a redraw
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum PendingPopupMenu {
@@ -588,4 +1155,64 @@ mod tests {
         assert_eq!(test_val.take(), PendingPopupMenu::Hide);
         assert_eq!(test_val, PendingPopupMenu::None);
     }
+
+    #[test]
+    fn test_decode_grid_line() {
+        let cells = Value::from(vec![
+            Value::from(vec![Value::from("a"), Value::from(3)]),
+            Value::from(vec![Value::from("b")]),
+            Value::from(vec![Value::from(" "), Value::from(0), Value::from(4)]),
+        ]);
+        let args = vec![Value::from(1), Value::from(2), Value::from(3), cells];
+
+        assert_eq!(
+            Ok(Some(RedrawEvent::GridLine {
+                grid: 1,
+                row: 2,
+                col_start: 3,
+                cells: vec![
+                    GridLineCell {
+                        text: "a".to_owned(),
+                        hl_id: Some(3),
+                        repeat: 1,
+                    },
+                    GridLineCell {
+                        text: "b".to_owned(),
+                        hl_id: Some(3),
+                        repeat: 1,
+                    },
+                    GridLineCell {
+                        text: " ".to_owned(),
+                        hl_id: Some(0),
+                        repeat: 4,
+                    },
+                ],
+            })),
+            RedrawEvent::decode("grid_line", args)
+        );
+    }
+
+    #[test]
+    fn test_decode_redraw() {
+        let params = vec![
+            Value::from(vec![
+                Value::from("grid_clear"),
+                Value::from(vec![Value::from(1)]),
+                Value::from(vec![Value::from("not a grid")]),
+                Value::from(vec![Value::from(2)]),
+            ]),
+            Value::from(vec![Value::from("unknown_event"), Value::Array(vec![])]),
+            Value::from(vec![Value::from("flush"), Value::Array(vec![])]),
+        ];
+
+        assert_eq!(
+            vec![
+                RedrawEvent::GridClear(1),
+                RedrawEvent::GridClear(2),
+                RedrawEvent::Flush
+            ],
+            decode_redraw(params)
+        );
+        assert!(RedrawEvent::decode("grid_cursor_goto", vec![Value::from(1)]).is_err());
+    }
 }
@@ -0,0 +1,408 @@
+use gio::prelude::*;
+use nvim_rs::Value;
+use tokio::sync::oneshot;
+
+use crate::nvim::NvimSession;
+use crate::spawn_timeout;
+use crate::value::ValueMapExt;
+
+/// Gets the menu mode and a menu tree in a single round trip. Like Neovim, MenuPopup is fired
+/// before getting the PopUp menu, with the menu mode as its pattern, so that it can be updated
+/// for what's under the cursor. The menu mode is looked up in `MENU_MODES`, passed along so that
+/// there's only one list of them.
+const MENU_GET_LUA: &str = r#"
+local path, menu_modes = ...
+local mode = vim.api.nvim_get_mode().mode
+local menu_mode = 'n'
+for _, prefix_mode in ipairs(menu_modes) do
+  if vim.startswith(mode, prefix_mode[1]) then
+    menu_mode = prefix_mode[2]
+    break
+  end
+end
+if path == 'PopUp' then
+  vim.api.nvim_exec_autocmds('MenuPopup', { pattern = menu_mode, modeline = false })
+end
+return { menu_mode, vim.fn.menu_get(path) }
+"#;
+
+/// Runs the rhs of a menu item the same way Neovim would, so mappings get used unless it's
+/// a :noremenu
+const MENU_RUN_LUA: &str = r#"
+local rhs, noremap = ...
+vim.api.nvim_feedkeys(vim.api.nvim_replace_termcodes(rhs, true, true, true), noremap and 'n' or 'm', false)
+"#;
+
+/// The rhs of a menu item for a single mode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MenuCommand {
+    pub rhs: String,
+    pub noremap: bool,
+}
+
+/// A node of a `:menu` tree, as returned by `menu_get()`
+#[derive(Debug, PartialEq, Eq)]
+pub struct MenuItem {
+    pub name: String,
+    pub enabled: bool,
+    pub command: Option<MenuCommand>,
+    /// The `icon=` of toolbar items
+    pub icon: Option<String>,
+    pub tooltip: Option<String>,
+    pub submenus: Vec<MenuItem>,
+}
+
+/// Prefixes of modes from `nvim_get_mode()` and the menu modes they map to, anything else being
+/// normal mode. The keys of `menu_get()` mappings are the menu modes, with terminal menus under
+/// "tl" as "t" holds the tooltip.
+const MENU_MODES: &[(&str, &str)] = &[
+    ("no", "o"),
+    ("v", "v"),
+    ("V", "v"),
+    ("\u{16}", "v"),
+    ("s", "s"),
+    ("S", "s"),
+    ("\u{13}", "s"),
+    ("i", "i"),
+    ("R", "i"),
+    ("c", "c"),
+    ("t", "tl"),
+];
+
+/// The menu mode for a mode from `nvim_get_mode()`
+fn menu_mode(mode: &str) -> &'static str {
+    MENU_MODES
+        .iter()
+        .find(|(prefix, _)| mode.starts_with(prefix))
+        .map_or("n", |(_, menu_mode)| menu_mode)
+}
+
+impl MenuItem {
+    /// Parse a menu item, keeping only the parts that exist in `mode`. Returns `None` for hidden
+    /// items and ones without anything to run in this mode.
+    fn from_value(value: &Value, mode: &str) -> Result<Option<Self>, String> {
+        let map = value
+            .as_map()
+            .ok_or("Menu item isn't a map")?
+            .to_attrs_map()?;
+
+        let name = map
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or("Menu item has no name")?
+            .to_owned();
+        if map
+            .get("hidden")
+            .and_then(|hidden| hidden.as_u64())
+            .is_some_and(|hidden| hidden != 0)
+        {
+            return Ok(None);
+        }
+
+        let mapping = map
+            .get("mappings")
+            .and_then(|mappings| mappings.as_map())
+            .and_then(|mappings| {
+                mappings
+                    .iter()
+                    .find(|(key, _)| key.as_str() == Some(mode))
+                    .and_then(|(_, mapping)| mapping.as_map())
+            })
+            .map(|mapping| mapping.to_attrs_map())
+            .transpose()?;
+        let flag = |key| {
+            mapping
+                .as_ref()
+                .and_then(|mapping| mapping.get(key))
+                .and_then(|val| val.as_u64())
+                .is_some_and(|val| val != 0)
+        };
+        let command = mapping
+            .as_ref()
+            .and_then(|mapping| mapping.get("rhs"))
+            .and_then(|rhs| rhs.as_str())
+            .map(|rhs| MenuCommand {
+                rhs: rhs.to_owned(),
+                noremap: flag("noremap"),
+            });
+
+        let submenus = match map.get("submenus").and_then(|submenus| submenus.as_array()) {
+            Some(submenus) => parse_menu(submenus, mode)?,
+            None => Vec::new(),
+        };
+
+        let string = |key| {
+            map.get(key)
+                .and_then(|val| val.as_str())
+                .filter(|val| !val.is_empty())
+                .map(str::to_owned)
+        };
+
+        let item = MenuItem {
+            enabled: flag("enabled"),
+            name,
+            command,
+            icon: string("icon"),
+            tooltip: string("tooltip"),
+            submenus,
+        };
+        if item.command.is_none() && item.submenus.is_empty() && !item.is_separator() {
+            return Ok(None);
+        }
+        Ok(Some(item))
+    }
+
+    pub fn is_separator(&self) -> bool {
+        self.name.len() > 1 && self.name.starts_with('-') && self.name.ends_with('-')
+    }
+
+    /// The name as a GTK mnemonic label, Vim marks the mnemonic with `&` instead of `_`
+    pub fn label(&self) -> String {
+        let mut label = String::with_capacity(self.name.len());
+        let mut chars = self.name.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '&' if chars.peek() == Some(&'&') => {
+                    chars.next();
+                    label.push('&');
+                }
+                '&' => label.push('_'),
+                '_' => label.push_str("__"),
+                c => label.push(c),
+            }
+        }
+        label
+    }
+}
+
+fn parse_menu(items: &[Value], mode: &str) -> Result<Vec<MenuItem>, String> {
+    Ok(items
+        .iter()
+        .map(|item| MenuItem::from_value(item, mode))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// Fetch the menu tree at `path` (e.g. "PopUp", or "" for all menus) for the current mode. The
+/// call runs on the session's runtime, so this can be awaited from the GTK main loop.
+pub async fn menu_get(nvim: NvimSession, path: &str) -> Result<Vec<MenuItem>, String> {
+    let (sender, receiver) = oneshot::channel();
+    let menu_modes = MENU_MODES
+        .iter()
+        .map(|&(prefix, menu_mode)| Value::from(vec![Value::from(prefix), Value::from(menu_mode)]))
+        .collect::<Vec<_>>();
+    let args = vec![Value::from(path), Value::from(menu_modes)];
+    nvim.clone().spawn(async move {
+        let _ = sender.send(nvim.timeout(nvim.exec_lua(MENU_GET_LUA, args)).await);
+    });
+    let res = receiver
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let res = res.as_array().ok_or("menu_get() didn't return a list")?;
+    let menu_mode = res.first().and_then(Value::as_str).unwrap_or("n");
+    let menus = res
+        .get(1)
+        .and_then(Value::as_array)
+        .ok_or("menu_get() didn't return a list")?;
+
+    // menu_get() on a path returns the menu itself, we want its children
+    let mut menus = parse_menu(menus, menu_mode)?;
+    if !path.is_empty() && menus.len() == 1 && menus[0].name == path {
+        return Ok(menus.remove(0).submenus);
+    }
+    Ok(menus)
+}
+
+/// Parse the output of `json_encode(menu_get(''))` for a mode from `mode()`
+pub fn parse_menu_json(json: &str, mode: &str) -> Result<Vec<MenuItem>, String> {
+    let menus: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let menus = menus.as_array().ok_or("menu_get() didn't return a list")?;
+    parse_menu(menus, menu_mode(mode))
+}
+
+/// Builds GIO menu models out of menu trees. The commands of the items are collected, so that
+/// an action with the index as its target can run them.
+pub struct MenuBuilder {
+    action: &'static str,
+    commands: Vec<MenuCommand>,
+}
+
+impl MenuBuilder {
+    pub fn new(action: &'static str) -> Self {
+        MenuBuilder {
+            action,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Build a menu, where Vim's separators split the items into sections
+    pub fn build(&mut self, items: &[MenuItem]) -> gio::Menu {
+        let menu = gio::Menu::new();
+        let mut section = gio::Menu::new();
+
+        for item in items {
+            if item.is_separator() {
+                if section.n_items() > 0 {
+                    menu.append_section(None, &section);
+                    section = gio::Menu::new();
+                }
+                continue;
+            }
+
+            if !item.submenus.is_empty() {
+                section.append_submenu(Some(&item.label()), &self.build(&item.submenus));
+            } else if let Some(ref command) = item.command {
+                // Items without an action are shown as insensitive
+                let detailed_action = item.enabled.then(|| {
+                    let target = self.push_command(command.clone());
+                    gio::Action::print_detailed_name(self.action, Some(&target.to_variant()))
+                });
+                section.append_item(&gio::MenuItem::new(
+                    Some(&item.label()),
+                    detailed_action.as_deref(),
+                ));
+            }
+        }
+
+        if section.n_items() > 0 {
+            menu.append_section(None, &section);
+        }
+        menu
+    }
+
+    /// Add a command without a menu item, returning the target of the action that runs it
+    pub fn push_command(&mut self, command: MenuCommand) -> u32 {
+        self.commands.push(command);
+        (self.commands.len() - 1) as u32
+    }
+
+    pub fn action(&self) -> &'static str {
+        self.action
+    }
+
+    pub fn into_commands(self) -> Vec<MenuCommand> {
+        self.commands
+    }
+}
+
+pub fn run_command(nvim: &NvimSession, command: &MenuCommand) {
+    let args = vec![
+        Value::from(command.rhs.as_str()),
+        Value::from(command.noremap),
+    ];
+    spawn_timeout!(nvim.exec_lua(MENU_RUN_LUA, args));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str) -> Value {
+        Value::Map(vec![(Value::from("name"), Value::from(name))])
+    }
+
+    fn leaf(name: &str, mode: &str, rhs: &str, enabled: bool) -> Value {
+        Value::Map(vec![
+            (Value::from("name"), Value::from(name)),
+            (
+                Value::from("mappings"),
+                Value::Map(vec![(
+                    Value::from(mode),
+                    Value::Map(vec![
+                        (Value::from("rhs"), Value::from(rhs)),
+                        (Value::from("noremap"), Value::from(1)),
+                        (Value::from("enabled"), Value::from(enabled as u64)),
+                    ]),
+                )]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_parse_menu() {
+        let menu = vec![
+            leaf("&Copy", "v", "\"+y", true),
+            item("-1-"),
+            leaf("Paste", "n", "\"+gP", false),
+            leaf("Visual only", "v", "gv", true),
+        ];
+
+        let items = parse_menu(&menu, "n").unwrap();
+        assert_eq!(2, items.len());
+        assert!(items[0].is_separator());
+        assert_eq!("Paste", items[1].name);
+        assert_eq!(
+            Some(MenuCommand {
+                rhs: "\"+gP".to_owned(),
+                noremap: true,
+            }),
+            items[1].command
+        );
+        assert!(!items[1].enabled);
+
+        let items = parse_menu(&menu, "v").unwrap();
+        assert_eq!(
+            vec!["&Copy", "-1-", "Visual only"],
+            items.iter().map(|i| i.name.as_str()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_label() {
+        let label = |name: &str| {
+            MenuItem {
+                name: name.to_owned(),
+                enabled: true,
+                command: None,
+                icon: None,
+                tooltip: None,
+                submenus: Vec::new(),
+            }
+            .label()
+        };
+
+        assert_eq!("_Copy", label("&Copy"));
+        assert_eq!("Search && Replace", label("Search && Replace"));
+        assert_eq!("snake__case", label("snake_case"));
+    }
+
+    #[test]
+    fn test_parse_menu_json() {
+        let json = r#"[
+            {"name": "File", "priority": 10, "submenus": [
+                {"name": "&Save", "mappings": {"n": {"rhs": ":w<CR>", "noremap": 1, "enabled": 1}}}
+            ]},
+            {"name": "ToolBar", "submenus": [
+                {"name": "Open", "icon": "document-open", "tooltip": "Open file",
+                 "mappings": {"n": {"rhs": ":browse e<CR>", "noremap": 0, "enabled": 1}}}
+            ]}
+        ]"#;
+
+        let items = parse_menu_json(json, "n").unwrap();
+        assert_eq!(2, items.len());
+        assert_eq!("&Save", items[0].submenus[0].name);
+        assert!(items[0].submenus[0].enabled);
+
+        let open = &items[1].submenus[0];
+        assert_eq!(Some("document-open"), open.icon.as_deref());
+        assert_eq!(Some("Open file"), open.tooltip.as_deref());
+        assert_eq!(Some(false), open.command.as_ref().map(|c| c.noremap));
+
+        assert!(parse_menu_json(json, "i").unwrap().is_empty());
+        assert!(parse_menu_json("{}", "n").is_err());
+    }
+
+    #[test]
+    fn test_menu_mode() {
+        assert_eq!("n", menu_mode("n"));
+        assert_eq!("v", menu_mode("V"));
+        assert_eq!("o", menu_mode("no"));
+        assert_eq!("i", menu_mode("i"));
+        assert_eq!("tl", menu_mode("t"));
+        assert_eq!("n", menu_mode("nt"));
+        assert_eq!("v", menu_mode("\u{16}"));
+    }
+}
@@ -1,13 +1,22 @@
 use once_cell::sync::Lazy;
 
-use gtk::{graphene::Rect, prelude::*, subclass::prelude::*};
+use fnv::FnvHashMap;
+
+use gtk::{
+    graphene::{Point, Rect},
+    prelude::*,
+    subclass::prelude::*,
+};
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    ops::Range,
     sync::{Arc, Weak},
 };
 
 use crate::{
+    cursor::Cursor,
+    grid::GridMap,
     popup_menu::PopupMenuPopover,
     render::*,
     shell::{RenderState, State},
@@ -46,14 +55,77 @@ impl NvimViewport {
         self.set_property("snapshot-cached", false);
     }
 
-    pub fn invalidate_snapshot_lines(&self, ui_model: &ui_model::UiModel) {
+    pub fn invalidate_snapshot_lines(&self, grids: &GridMap) {
+        self.imp()
+            .inner
+            .borrow_mut()
+            .invalidate_snapshot_lines(grids);
+    }
+
+    /// Animate the scroll of rows `top..bot` of a grid by `rows`, starting from the positions the
+    /// lines had in the last frame. Does nothing if animations are disabled.
+    pub fn animate_scroll(&self, grid: u64, top: usize, bot: usize, rows: i64) {
+        if !self.settings().is_gtk_enable_animations() {
+            return;
+        }
+
+        let imp = self.imp();
+        let now = self
+            .frame_clock()
+            .map_or_else(glib::monotonic_time, |clock| clock.frame_time());
+        imp.inner
+            .borrow_mut()
+            .animate_scroll(grid, top..bot, rows, now);
+        imp.ensure_animation_tick();
+    }
+
+    /// Apply the redraws queued on the shell state right before the next frame gets drawn. Only
+    /// one frame is requested no matter how often this is called in between.
+    pub fn request_frame(&self) {
+        let imp = self.imp();
+        if imp.frame_requested.replace(true) {
+            return;
+        }
+
+        self.add_tick_callback(|obj, _| {
+            let imp = obj.imp();
+            imp.frame_requested.set(false);
+
+            let state = imp.inner.borrow().state.upgrade();
+            if let Some(state) = state {
+                let popupmenu = state.borrow_mut().prepare_frame();
+                state.borrow().popupmenu_flush(popupmenu);
+            }
+            glib::ControlFlow::Break
+        });
+    }
+
+    /// Move the cached render nodes of rows `top..bot` of a grid along with a scroll of whole
+    /// lines, so only the rows scrolled into view need to be drawn again
+    pub fn scroll_snapshot_lines(&self, grid: u64, top: usize, bot: usize, rows: i64) {
         self.imp()
             .inner
             .borrow_mut()
-            .invalidate_snapshot_lines(ui_model);
+            .scroll_snapshot_lines(grid, top..bot, rows);
+    }
+
+    /// Briefly flash the viewport, for the visual bell
+    pub fn flash(&self) {
+        let imp = self.imp();
+        let now = self
+            .frame_clock()
+            .map_or_else(glib::monotonic_time, |clock| clock.frame_time());
+        imp.inner.borrow_mut().visual_bell = Some(now);
+        imp.ensure_animation_tick();
     }
 }
 
+/// How long it takes for a smooth scroll to settle, in microseconds
+const SCROLL_DURATION: i64 = 150_000;
+
+/// How long the flash of the visual bell takes to fade out, in microseconds
+const VISUAL_BELL_DURATION: i64 = 200_000;
+
 struct CachedLineSnapshot {
     snapshot: Option<gsk::RenderNode>,
     dirty: bool,
@@ -75,45 +147,43 @@ impl Default for CachedLineSnapshot {
     }
 }
 
-/** The inner state structure for the viewport widget, for holding non-glib types (e.g. ones that
- * need inferior mutability) */
+/// The cached render nodes for each line of a single grid
 #[derive(Default)]
-struct NvimViewportInner {
-    state: Weak<UiMutex<State>>,
-    snapshot_cache: Vec<CachedLineSnapshot>,
-    snapshot_dimensions: Option<(usize, usize)>,
+struct GridSnapshotCache {
+    lines: Vec<CachedLineSnapshot>,
+    dimensions: Option<(usize, usize)>,
 }
 
-impl NvimViewportInner {
-    fn clear_snapshot_cache(&mut self) {
-        self.snapshot_cache.clear();
-        self.snapshot_dimensions = None;
+impl GridSnapshotCache {
+    fn clear(&mut self) {
+        self.lines.clear();
+        self.dimensions = None;
     }
 
     fn has_cached_snapshot(&self) -> bool {
-        self.snapshot_cache.iter().any(|line| !line.dirty)
+        self.lines.iter().any(|line| !line.dirty)
     }
 
     fn ensure_snapshot_cache(&mut self, rows: usize, columns: usize) {
-        if self.snapshot_dimensions == Some((rows, columns)) {
+        if self.dimensions == Some((rows, columns)) {
             return;
         }
 
-        self.snapshot_cache = std::iter::repeat_with(CachedLineSnapshot::default)
+        self.lines = std::iter::repeat_with(CachedLineSnapshot::default)
             .take(rows)
             .collect();
-        self.snapshot_dimensions = Some((rows, columns));
+        self.dimensions = Some((rows, columns));
     }
 
     fn invalidate_snapshot_lines(&mut self, ui_model: &ui_model::UiModel) {
-        if self.snapshot_dimensions != Some((ui_model.rows, ui_model.columns)) {
-            self.clear_snapshot_cache();
+        if self.dimensions != Some((ui_model.rows, ui_model.columns)) {
+            self.clear();
             return;
         }
 
         for (row, line) in ui_model.model().iter().enumerate() {
             if line.dirty_line
-                && let Some(cached_line) = self.snapshot_cache.get_mut(row)
+                && let Some(cached_line) = self.lines.get_mut(row)
             {
                 cached_line.invalidate();
             }
@@ -121,12 +191,220 @@ impl NvimViewportInner {
     }
 }
 
+/// The render node of a line that has been scrolled out of its region, but may still be visible
+/// while a scroll animation is running
+struct ScrolledOutLine {
+    node: gsk::RenderNode,
+    /// The row the line was drawn at
+    row: usize,
+    /// How many rows the line has moved since it was drawn
+    shift: f64,
+}
+
+/// An in-progress smooth scroll of a region of a grid
+struct ScrollAnimation {
+    region: Range<usize>,
+    /// How many rows away from their final position the lines were when the animation started
+    offset: f64,
+    start: i64,
+    /// Whether a frame has been drawn since the last scroll. If not, the cached line nodes don't
+    /// match the positions the lines were last drawn at.
+    drawn: bool,
+    scrolled_out: Vec<ScrolledOutLine>,
+}
+
+impl ScrollAnimation {
+    fn progress(&self, now: i64) -> f64 {
+        ((now - self.start) as f64 / SCROLL_DURATION as f64).clamp(0.0, 1.0)
+    }
+
+    fn is_finished(&self, now: i64) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// The remaining offset of the region in rows, easing out as the animation progresses
+    fn offset_at(&self, now: i64) -> f64 {
+        self.offset * (1.0 - self.progress(now)).powi(3)
+    }
+
+    fn snapshot(
+        &self,
+        snapshot: &gtk::Snapshot,
+        lines: &[CachedLineSnapshot],
+        now: i64,
+        width: f32,
+        line_height: f64,
+    ) {
+        for (row, line) in lines
+            .iter()
+            .enumerate()
+            .filter(|(row, _)| !self.region.contains(row))
+        {
+            if let Some(node) = line.snapshot.as_ref() {
+                append_nvim_line(snapshot, node, row as f64, line_height);
+            }
+        }
+
+        snapshot.push_clip(&Rect::new(
+            0.0,
+            (self.region.start as f64 * line_height) as f32,
+            width,
+            (self.region.len() as f64 * line_height) as f32,
+        ));
+        snapshot.save();
+        snapshot.translate(&Point::new(0.0, (self.offset_at(now) * line_height) as f32));
+
+        for (row, line) in self.region.clone().zip(&lines[self.region.clone()]) {
+            if let Some(node) = line.snapshot.as_ref() {
+                append_nvim_line(snapshot, node, row as f64, line_height);
+            }
+        }
+        for line in &self.scrolled_out {
+            append_nvim_line(
+                snapshot,
+                &line.node,
+                line.row as f64 + line.shift,
+                line_height,
+            );
+        }
+
+        snapshot.restore();
+        snapshot.pop();
+    }
+}
+
+/** The inner state structure for the viewport widget, for holding non-glib types (e.g. ones that
+ * need inferior mutability) */
+#[derive(Default)]
+struct NvimViewportInner {
+    state: Weak<UiMutex<State>>,
+    snapshot_cache: FnvHashMap<u64, GridSnapshotCache>,
+    scroll_animations: FnvHashMap<u64, ScrollAnimation>,
+    /// When the visual bell started flashing
+    visual_bell: Option<i64>,
+}
+
+impl NvimViewportInner {
+    fn clear_snapshot_cache(&mut self) {
+        self.snapshot_cache.clear();
+        self.scroll_animations.clear();
+    }
+
+    fn animate_scroll(&mut self, grid: u64, region: Range<usize>, rows: i64, now: i64) {
+        let Some(cache) = self.snapshot_cache.get(&grid) else {
+            return;
+        };
+        if rows == 0 || region.end > cache.lines.len() {
+            return;
+        }
+
+        let (offset, mut scrolled_out) = match self.scroll_animations.remove(&grid) {
+            None => (0.0, Vec::new()),
+            Some(animation) if animation.drawn && animation.region == region => {
+                (animation.offset_at(now), animation.scrolled_out)
+            }
+            // We can't tell where the lines were last drawn, so just jump to the final position
+            Some(_) => return,
+        };
+
+        let height = region.len() as i64;
+        let rows_out = rows.abs().min(height) as usize;
+        let scrolled_rows = if rows > 0 {
+            region.start..region.start + rows_out
+        } else {
+            region.end - rows_out..region.end
+        };
+        for line in &mut scrolled_out {
+            line.shift -= rows as f64;
+        }
+        scrolled_out.extend(scrolled_rows.filter_map(|row| {
+            Some(ScrolledOutLine {
+                node: cache.lines[row].snapshot.clone()?,
+                row,
+                shift: -rows as f64,
+            })
+        }));
+
+        let offset = offset + rows as f64;
+        if offset.abs() > height as f64 {
+            // Scrolled further than the region is tall, animating that would just be a blur
+            return;
+        }
+
+        // Forget the lines that can't come back into view before the animation settles
+        scrolled_out.retain(|line| {
+            let row = line.row as f64 + line.shift;
+            let (lo, hi) = if offset > 0.0 {
+                (row, row + offset)
+            } else {
+                (row + offset, row)
+            };
+            hi > region.start as f64 - 1.0 && lo < region.end as f64
+        });
+
+        self.scroll_animations.insert(
+            grid,
+            ScrollAnimation {
+                region,
+                offset,
+                start: now,
+                drawn: false,
+                scrolled_out,
+            },
+        );
+    }
+
+    fn scroll_snapshot_lines(&mut self, grid: u64, region: Range<usize>, rows: i64) {
+        let Some(cache) = self.snapshot_cache.get_mut(&grid) else {
+            return;
+        };
+        if region.end > cache.lines.len() {
+            return;
+        }
+
+        // The rows scrolled into view end up with the nodes of the rows scrolled out, those get
+        // invalidated along with the lines Neovim clears
+        let lines = &mut cache.lines[region];
+        let rows_out = (rows.unsigned_abs() as usize).min(lines.len());
+        if rows > 0 {
+            lines.rotate_left(rows_out);
+        } else {
+            lines.rotate_right(rows_out);
+        }
+    }
+
+    fn has_cached_snapshot(&self) -> bool {
+        self.snapshot_cache
+            .values()
+            .any(GridSnapshotCache::has_cached_snapshot)
+    }
+
+    fn invalidate_snapshot_lines(&mut self, grids: &GridMap) {
+        // Drop the caches of any grids that have been destroyed
+        self.snapshot_cache
+            .retain(|idx, _| grids.get(*idx).is_some());
+        self.scroll_animations.retain(|idx, animation| {
+            grids
+                .get(*idx)
+                .is_some_and(|grid| animation.region.end <= grid.model().rows)
+        });
+
+        for (idx, grid) in grids.iter() {
+            if let Some(cache) = self.snapshot_cache.get_mut(&idx) {
+                cache.invalidate_snapshot_lines(grid.model());
+            }
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct NvimViewportObject {
     inner: RefCell<NvimViewportInner>,
     context_menu: glib::WeakRef<gtk::PopoverMenu>,
     completion_popover: glib::WeakRef<PopupMenuPopover>,
     ext_cmdline: glib::WeakRef<gtk::Popover>,
+    animation_tick: Cell<bool>,
+    frame_requested: Cell<bool>,
 }
 
 #[glib::object_subclass]
@@ -250,7 +528,7 @@ impl WidgetImpl for NvimViewportObject {
 
     fn snapshot(&self, snapshot_in: &gtk::Snapshot) {
         let obj = self.obj();
-        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *self.inner.borrow_mut();
         let state = match inner.state.upgrade() {
             Some(state) => state,
             None => return,
@@ -270,36 +548,108 @@ impl WidgetImpl for NvimViewportObject {
             // Render scenes get pretty huge here, so we cache them per line and only rebuild the
             // lines touched by the last redraw.
             let font_ctx = &render_state.font_ctx;
-            let ui_model = match state.grids.current_model() {
-                Some(ui_model) => ui_model,
-                None => return,
-            };
-
-            // Recreate the full cache only when the grid dimensions change. Otherwise we keep the
-            // previous render nodes and rebuild only dirty lines below.
-            inner.ensure_snapshot_cache(ui_model.rows, ui_model.columns);
-            debug_assert_eq!(ui_model.model().len(), inner.snapshot_cache.len());
+            let cell_metrics = font_ctx.cell_metrics();
             let push_opacity = transparency.filled_alpha < 0.99999;
             if push_opacity {
                 snapshot_in.push_opacity(transparency.filled_alpha)
             }
 
-            for (row, (line, cached_line)) in ui_model
-                .model()
-                .iter()
-                .zip(inner.snapshot_cache.iter_mut())
-                .enumerate()
-            {
-                if cached_line.dirty {
-                    cached_line.snapshot = snapshot_nvim_line(font_ctx, line, row, hl);
-                    cached_line.dirty = false;
-                }
+            let float_style = state.float_style();
+            let now = obj
+                .frame_clock()
+                .map_or_else(glib::monotonic_time, |clock| clock.frame_time());
 
-                let Some(cached_snapshot) = cached_line.snapshot.as_ref() else {
+            // Composite each grid at its own position, from the bottom of the stack to the top
+            for grid_idx in state.grids.visible_grids() {
+                let Some(grid) = state.grids.get(grid_idx) else {
                     continue;
                 };
+                let ui_model = grid.model();
+
+                // Recreate the full cache only when the grid dimensions change. Otherwise we keep
+                // the previous render nodes and rebuild only dirty lines below.
+                let cache = inner.snapshot_cache.entry(grid_idx).or_default();
+                cache.ensure_snapshot_cache(ui_model.rows, ui_model.columns);
+                debug_assert_eq!(ui_model.model().len(), cache.lines.len());
+
+                let (row, col) = state.grids.origin(grid_idx);
+                let (x, y) = (
+                    col * cell_metrics.char_width,
+                    row * cell_metrics.line_height,
+                );
+                snapshot_in.save();
+                snapshot_in.translate(&Point::new(x as f32, y as f32));
+
+                // Floating windows get drawn as their own layer, optionally with a drop shadow and
+                // rounded corners
+                let float_outline = grid.is_float().then(|| {
+                    gsk::RoundedRect::from_rect(
+                        Rect::new(
+                            0.0,
+                            0.0,
+                            (ui_model.columns as f64 * cell_metrics.char_width) as f32,
+                            (ui_model.rows as f64 * cell_metrics.line_height) as f32,
+                        ),
+                        float_style.corner_radius,
+                    )
+                });
+                if let Some(ref outline) = float_outline {
+                    if float_style.shadow {
+                        snapshot_in.append_outset_shadow(
+                            outline,
+                            &gdk::RGBA::new(0.0, 0.0, 0.0, 0.35),
+                            0.0,
+                            2.0,
+                            0.0,
+                            8.0,
+                        );
+                    }
+                    snapshot_in.push_rounded_clip(outline);
+                }
+
+                // Only floats need cells with the default background to cover what's below them.
+                // Window grids sit over parts of the default grid Neovim leaves blank, so they keep
+                // showing the viewport background at `TransparencySettings::background_alpha`.
+                let opaque = grid.is_float();
+                for (line, cached_line) in ui_model
+                    .model()
+                    .iter()
+                    .zip(cache.lines.iter_mut())
+                    .filter(|(_, cached_line)| cached_line.dirty)
+                {
+                    cached_line.snapshot = snapshot_nvim_line(font_ctx, line, hl, opaque);
+                    cached_line.dirty = false;
+                }
+
+                match inner.scroll_animations.get_mut(&grid_idx) {
+                    Some(animation) => {
+                        animation.drawn = true;
+                        animation.snapshot(
+                            snapshot_in,
+                            &cache.lines,
+                            now,
+                            (ui_model.columns as f64 * cell_metrics.char_width) as f32,
+                            cell_metrics.line_height,
+                        );
+                    }
+                    None => {
+                        for (row, line) in cache.lines.iter().enumerate() {
+                            if let Some(node) = line.snapshot.as_ref() {
+                                append_nvim_line(
+                                    snapshot_in,
+                                    node,
+                                    row as f64,
+                                    cell_metrics.line_height,
+                                );
+                            }
+                        }
+                    }
+                }
 
-                snapshot_in.append_node(cached_snapshot);
+                if float_outline.is_some() {
+                    snapshot_in.pop();
+                }
+                snapshot_in.restore();
             }
 
             if push_opacity {
@@ -307,9 +657,43 @@ impl WidgetImpl for NvimViewportObject {
             }
 
             if let Some(cursor) = state.cursor()
-                && let Some(model) = state.grids.current_model()
+                && let Some(grid) = state.grids.current()
             {
-                snapshot_cursor(snapshot_in, cursor, font_ctx, model, hl, transparency);
+                let (row, col) = state.grids.origin(state.grids.current_id());
+                let origin = (
+                    col * cell_metrics.char_width,
+                    row * cell_metrics.line_height,
+                );
+                snapshot_in.save();
+                snapshot_in.translate(&Point::new(origin.0 as f32, origin.1 as f32));
+                snapshot_cursor(
+                    snapshot_in,
+                    cursor,
+                    font_ctx,
+                    grid.model(),
+                    hl,
+                    transparency,
+                    origin,
+                    obj.settings().is_gtk_enable_animations().then_some(now),
+                );
+                if let Some(preedit) = state.preedit() {
+                    snapshot_preedit(snapshot_in, preedit, font_ctx, grid.model(), hl);
+                }
+                snapshot_in.restore();
+
+                if cursor.is_moving() {
+                    self.ensure_animation_tick();
+                }
+            }
+
+            if let Some(start) = inner.visual_bell {
+                let progress = (now - start) as f64 / VISUAL_BELL_DURATION as f64;
+                if progress < 1.0 {
+                    snapshot_in.append_color(
+                        &hl.fg().to_rgbo(0.3 * (1.0 - progress)),
+                        &Rect::new(0.0, 0.0, obj.width() as f32, obj.height() as f32),
+                    );
+                }
             }
         } else {
             self.snapshot_initializing(snapshot_in, &render_state);
@@ -318,6 +702,41 @@ impl WidgetImpl for NvimViewportObject {
 }
 
 impl NvimViewportObject {
+    /// Keep redrawing every frame until all scroll animations have settled, the cursor has
+    /// stopped moving and the visual bell has faded out
+    fn ensure_animation_tick(&self) {
+        if self.animation_tick.replace(true) {
+            return;
+        }
+
+        self.obj().add_tick_callback(|obj, clock| {
+            let imp = obj.imp();
+            let now = clock.frame_time();
+            let mut inner = imp.inner.borrow_mut();
+            inner
+                .scroll_animations
+                .retain(|_, animation| !animation.is_finished(now));
+            if inner
+                .visual_bell
+                .is_some_and(|start| now - start >= VISUAL_BELL_DURATION)
+            {
+                inner.visual_bell = None;
+            }
+            let cursor_moving = inner
+                .state
+                .upgrade()
+                .is_some_and(|state| state.borrow().cursor().is_some_and(Cursor::is_moving));
+            obj.queue_draw();
+
+            if inner.scroll_animations.is_empty() && !cursor_moving && inner.visual_bell.is_none() {
+                imp.animation_tick.set(false);
+                glib::ControlFlow::Break
+            } else {
+                glib::ControlFlow::Continue
+            }
+        });
+    }
+
     fn snapshot_initializing(&self, snapshot: &gtk::Snapshot, render_state: &RenderState) {
         let obj = self.obj();
         let layout = obj.create_pango_layout(Some("Loading…"));
@@ -340,17 +759,17 @@ impl NvimViewportObject {
 mod tests {
     use super::*;
 
-    fn clean_snapshot_cache(inner: &mut NvimViewportInner) {
-        for line in &mut inner.snapshot_cache {
+    fn clean_snapshot_cache(cache: &mut GridSnapshotCache) {
+        for line in &mut cache.lines {
             line.dirty = false;
         }
     }
 
     #[test]
     fn invalidate_snapshot_lines_only_marks_dirty_rows() {
-        let mut inner = NvimViewportInner::default();
-        inner.ensure_snapshot_cache(3, 4);
-        clean_snapshot_cache(&mut inner);
+        let mut cache = GridSnapshotCache::default();
+        cache.ensure_snapshot_cache(3, 4);
+        clean_snapshot_cache(&mut cache);
 
         let mut model = ui_model::UiModel::new(3, 4);
         for line in model.model_mut().iter_mut() {
@@ -358,24 +777,94 @@ mod tests {
         }
         model.model_mut()[1].dirty_line = true;
 
-        inner.invalidate_snapshot_lines(&model);
+        cache.invalidate_snapshot_lines(&model);
 
-        assert!(!inner.snapshot_cache[0].dirty);
-        assert!(inner.snapshot_cache[1].dirty);
-        assert!(!inner.snapshot_cache[2].dirty);
-        assert_eq!(inner.snapshot_dimensions, Some((3, 4)));
+        assert!(!cache.lines[0].dirty);
+        assert!(cache.lines[1].dirty);
+        assert!(!cache.lines[2].dirty);
+        assert_eq!(cache.dimensions, Some((3, 4)));
     }
 
     #[test]
     fn invalidate_snapshot_lines_clears_cache_on_dimension_change() {
-        let mut inner = NvimViewportInner::default();
-        inner.ensure_snapshot_cache(3, 4);
-        clean_snapshot_cache(&mut inner);
+        let mut cache = GridSnapshotCache::default();
+        cache.ensure_snapshot_cache(3, 4);
+        clean_snapshot_cache(&mut cache);
 
         let model = ui_model::UiModel::new(4, 4);
-        inner.invalidate_snapshot_lines(&model);
+        cache.invalidate_snapshot_lines(&model);
+
+        assert!(cache.lines.is_empty());
+        assert_eq!(cache.dimensions, None);
+    }
+
+    #[test]
+    fn invalidate_snapshot_lines_drops_destroyed_grids() {
+        let mut inner = NvimViewportInner::default();
+        let mut grids = GridMap::new();
+        grids.get_or_create(1).resize(4, 3);
+        grids.get_or_create(2).resize(4, 3);
+
+        for idx in [1, 2] {
+            let cache = inner.snapshot_cache.entry(idx).or_default();
+            cache.ensure_snapshot_cache(3, 4);
+            clean_snapshot_cache(cache);
+        }
+
+        grids.destroy(2);
+        inner.invalidate_snapshot_lines(&grids);
+
+        assert!(inner.snapshot_cache.contains_key(&1));
+        assert!(!inner.snapshot_cache.contains_key(&2));
+    }
+
+    #[test]
+    fn animate_scroll_accumulates_offset() {
+        let mut inner = NvimViewportInner::default();
+        inner
+            .snapshot_cache
+            .entry(1)
+            .or_default()
+            .ensure_snapshot_cache(10, 4);
+
+        inner.animate_scroll(1, 0..8, 2, 0);
+        let animation = &inner.scroll_animations[&1];
+        assert_eq!(2.0, animation.offset_at(0));
+        assert_eq!(0.0, animation.offset_at(SCROLL_DURATION));
+
+        // Scrolls before the first frame was drawn can't be animated
+        inner.animate_scroll(1, 0..8, 1, 0);
+        assert!(!inner.scroll_animations.contains_key(&1));
+
+        inner.animate_scroll(1, 0..8, 2, 0);
+        inner.scroll_animations.get_mut(&1).unwrap().drawn = true;
+        inner.animate_scroll(1, 0..8, -3, 0);
+        assert_eq!(-1.0, inner.scroll_animations[&1].offset_at(0));
+
+        // Scrolling further than the region is tall just jumps
+        inner.scroll_animations.get_mut(&1).unwrap().drawn = true;
+        inner.animate_scroll(1, 0..8, 9, 0);
+        assert!(!inner.scroll_animations.contains_key(&1));
+    }
+
+    #[test]
+    fn scroll_snapshot_lines_moves_cached_lines() {
+        let mut inner = NvimViewportInner::default();
+        let cache = inner.snapshot_cache.entry(1).or_default();
+        cache.ensure_snapshot_cache(10, 4);
+        clean_snapshot_cache(cache);
+        cache.lines[3].dirty = true;
+
+        let mut model = ui_model::UiModel::new(10, 4);
+        for line in model.model_mut().iter_mut() {
+            line.dirty_line = false;
+        }
+        model.scroll(0, 7, 0, 3, 2);
+        inner.scroll_snapshot_lines(1, 0..8, 2);
 
-        assert!(inner.snapshot_cache.is_empty());
-        assert_eq!(inner.snapshot_dimensions, None);
+        let cache = inner.snapshot_cache.get_mut(&1).unwrap();
+        cache.invalidate_snapshot_lines(&model);
+        let dirty: Vec<_> = (0..10).filter(|row| cache.lines[*row].dirty).collect();
+        assert_eq!(vec![1, 6, 7], dirty);
     }
 }
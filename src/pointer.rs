@@ -0,0 +1,131 @@
+use gtk::prelude::*;
+
+use crate::highlight::Separator;
+
+/// The pointer cursor names for the indices of Vim's 'mouseshape' shapes, which Neovim uses for
+/// `mouse_shape` in mode_info_set
+const MOUSE_SHAPES: [&str; 16] = [
+    "default",     // arrow
+    "none",        // blank
+    "text",        // beam
+    "ns-resize",   // updown
+    "ns-resize",   // udsizing
+    "ew-resize",   // leftright
+    "ew-resize",   // lrsizing
+    "wait",        // busy
+    "not-allowed", // no
+    "crosshair",   // crosshair
+    "pointer",     // hand1
+    "pointer",     // hand2
+    "crosshair",   // pencil
+    "help",        // question
+    "default",     // rightup-arrow
+    "default",     // up-arrow
+];
+
+/// The pointer cursor for a mode's `mouse_shape`. Neovim doesn't implement 'mouseshape' and sends
+/// 0 for every mode, so that is treated as not set.
+pub fn mouse_shape_name(shape: u64) -> Option<&'static str> {
+    match shape {
+        0 => None,
+        shape => MOUSE_SHAPES.get(shape as usize).copied(),
+    }
+}
+
+/// What the pointer is over in the viewport
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Hover {
+    #[default]
+    Text,
+    /// A link that can be opened with Ctrl+click
+    Link,
+    /// A window separator or status line that can be dragged
+    Separator(Separator),
+}
+
+/// Everything that decides which pointer cursor is shown over the viewport. We cache the current
+/// cursor, since otherwise we'd end up creating a new one for every single motion event.
+pub struct Pointer {
+    /// Hidden while typing when 'mousehide' is set, until the pointer moves again
+    pub hidden: bool,
+    pub hover: Hover,
+    pub busy: bool,
+    pub mouse_enabled: bool,
+    /// The shape for the current mode, if Neovim sends one
+    pub mode_shape: Option<&'static str>,
+    current: Option<&'static str>,
+}
+
+impl Pointer {
+    pub fn new() -> Self {
+        Pointer {
+            hidden: false,
+            hover: Hover::Text,
+            busy: false,
+            mouse_enabled: true,
+            mode_shape: None,
+            current: None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.hidden {
+            return "none";
+        }
+        if self.busy {
+            return "wait";
+        }
+        if !self.mouse_enabled {
+            return "default";
+        }
+
+        match self.hover {
+            Hover::Link => "pointer",
+            Hover::Separator(Separator::Vertical) => "ew-resize",
+            Hover::Separator(Separator::Horizontal) => "ns-resize",
+            Hover::Text => self.mode_shape.unwrap_or("text"),
+        }
+    }
+
+    /// Set the pointer cursor on the widget, if it changed
+    pub fn apply(&mut self, widget: &impl IsA<gtk::Widget>) {
+        let name = self.name();
+        if Some(name) == self.current {
+            return;
+        }
+
+        self.current = Some(name);
+        widget.set_cursor(gdk::Cursor::from_name(name, None).as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_name() {
+        let mut pointer = Pointer::new();
+        assert_eq!("text", pointer.name());
+
+        pointer.hover = Hover::Separator(Separator::Vertical);
+        assert_eq!("ew-resize", pointer.name());
+
+        pointer.busy = true;
+        assert_eq!("wait", pointer.name());
+
+        pointer.hidden = true;
+        assert_eq!("none", pointer.name());
+
+        pointer.hidden = false;
+        pointer.busy = false;
+        pointer.hover = Hover::Text;
+        pointer.mode_shape = mouse_shape_name(0);
+        assert_eq!("text", pointer.name());
+        pointer.mode_shape = mouse_shape_name(7);
+        assert_eq!("wait", pointer.name());
+
+        pointer.mouse_enabled = false;
+        assert_eq!("default", pointer.name());
+    }
+}
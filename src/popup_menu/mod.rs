@@ -18,7 +18,7 @@ use crate::{
     highlight::HighlightMap,
     nvim::{self, ErrorReport, NeovimClient, PendingPopupMenu, PopupMenuItem},
     render::{self, CellMetrics},
-    shell::RenderState,
+    shell::{RenderState, TransparencySettings},
     spawn_timeout,
     ui_model::ModelRect,
 };
@@ -39,6 +39,7 @@ pub struct State {
     info_scroll: gtk::ScrolledWindow,
     info_label: gtk::Label,
     css_provider: gtk::CssProvider,
+    transparency: TransparencySettings,
     open: bool,
     row_height: i32,
     width_limit: i32,
@@ -115,6 +116,7 @@ impl State {
             item_scroll,
             info_scroll,
             css_provider,
+            transparency: TransparencySettings::new(),
             info_label,
             row_height: 0,
             width_limit: 0,
@@ -276,9 +278,14 @@ impl State {
 
     fn update_css(&self, hl: &HighlightMap, font_ctx: &render::Context) {
         let font_desc = font_ctx.font_description();
+        // pumblend is applied on top of any transparency the user configured
+        let bg_alpha = hl.pmenu_bg_alpha() * self.transparency.filled_alpha;
 
         self.css_provider.load_from_data(&format!(
-            "listview.nvim-popupmenu-list {{\
+            "popover.nvim-popupmenu > contents {{\
+                    background-color: {bg};\
+                }}\
+                listview.nvim-popupmenu-list {{\
                     background-color: {bg};\
                     font-family: \"{font}\";\
                     font-size: {size}pt;\
@@ -295,7 +302,7 @@ impl State {
             fg_sel = hl.pmenu_fg_sel().to_hex(),
             bg_sel = hl.pmenu_bg_sel().to_hex(),
             fg = hl.pmenu_fg().to_hex(),
-            bg = hl.pmenu_bg().to_hex(),
+            bg = hl.pmenu_bg().to_rgbo(bg_alpha),
             font = font_desc.family().unwrap().as_str(),
             size = (font_desc.size() as f64 / pango::SCALE as f64),
         ));
@@ -372,6 +379,7 @@ impl PopupMenu {
         popover.set_child(Some(&content));
         popover.add_css_class("background");
         popover.add_css_class("nvim-popover");
+        popover.add_css_class("nvim-popupmenu");
         popover.set_position(gtk::PositionType::Top);
 
         content.append(&state.item_scroll);
@@ -520,6 +528,12 @@ impl PopupMenu {
         self.state.borrow().update_css(hl, font_ctx);
     }
 
+    pub fn set_transparency(&self, transparency: TransparencySettings, render_state: &RenderState) {
+        let mut state = self.state.borrow_mut();
+        state.transparency = transparency;
+        state.update_css(&render_state.hl, &render_state.font_ctx);
+    }
+
     // Hide/show the popupmenu, according to the current pending status
     pub fn flush(
         &self,
@@ -604,7 +618,7 @@ pub fn list_select(state: &mut State, idx: u32, last_command: &str) {
                 .collect()
         };
 
-        nvim.block_timeout(nvim.input(&apply_command)).report_err();
+        nvim.queue_input(apply_command);
     }
     state.prev_selected = Some(idx);
 }
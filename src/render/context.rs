@@ -1,14 +1,19 @@
+use std::cell::RefCell;
 use std::collections::HashSet;
 
+use log::debug;
+
 use pango::{self, prelude::*};
 
 use super::itemize::ItemizeIterator;
+use super::shape_cache::{CacheStats, ShapeCache};
 use crate::ui_model::StyledLine;
 
 pub struct Context {
     font_metrics: FontMetrix,
     font_features: FontFeatures,
     line_space: i32,
+    shape_cache: RefCell<ShapeCache>,
 }
 
 impl Context {
@@ -17,21 +22,48 @@ impl Context {
             line_space: 0,
             font_metrics: FontMetrix::new(pango_context, 0),
             font_features: FontFeatures::new(),
+            shape_cache: RefCell::new(ShapeCache::new()),
         }
     }
 
     pub fn update(&mut self, pango_context: pango::Context) {
         self.font_metrics = FontMetrix::new(pango_context, self.line_space);
+        self.clear_shape_cache();
     }
 
     pub fn update_font_features(&mut self, font_features: FontFeatures) {
         self.font_features = font_features;
+        self.clear_shape_cache();
     }
 
     pub fn update_line_space(&mut self, line_space: i32) {
         self.line_space = line_space;
         let pango_context = self.font_metrics.pango_context.clone();
         self.font_metrics = FontMetrix::new(pango_context, self.line_space);
+        self.clear_shape_cache();
+    }
+
+    /// Shape a run of text, reusing the glyphs from the last time it was shaped with the same font
+    pub fn shape(&self, text: &str, analysis: &pango::Analysis) -> pango::GlyphString {
+        self.shape_cache.borrow_mut().shape(text, analysis)
+    }
+
+    pub fn shape_cache_stats(&self) -> CacheStats {
+        self.shape_cache.borrow().stats()
+    }
+
+    fn clear_shape_cache(&mut self) {
+        let shape_cache = self.shape_cache.get_mut();
+        let stats = shape_cache.stats();
+        debug!(
+            "Clearing {} shaped runs: {} hits, {} misses, {} evictions ({:.1}% hit rate)",
+            shape_cache.len(),
+            stats.hits,
+            stats.misses,
+            stats.evictions,
+            stats.hit_rate() * 100.0
+        );
+        shape_cache.clear();
     }
 
     pub fn itemize(&self, line: &StyledLine) -> Vec<pango::Item> {
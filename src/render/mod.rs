@@ -1,19 +1,23 @@
 mod context;
 mod itemize;
+mod shape_cache;
 
 pub use self::context::{CellMetrics, Context, FontFeatures};
 
-use log::warn;
+use log::{debug, warn};
 
 use crate::{
     color,
     cursor::{Cursor, CursorRedrawCb, cursor_rect},
     highlight::HighlightMap,
-    shell::TransparencySettings,
+    shell::{Preedit, TransparencySettings},
     ui_model,
 };
 
-use gtk::{graphene::Rect, prelude::*};
+use gtk::{
+    graphene::{Point, Rect},
+    prelude::*,
+};
 
 /// A single step in a render plan
 #[derive(Clone, Copy)]
@@ -44,8 +48,8 @@ impl<'a> RenderStep<'a> {
         let pos = cell_metrics.get_pixel_coords(self.pos);
         let len = cell_metrics.get_cell_len(self.len);
         match self.kind {
-            RenderStepKind::Background =>
-                snapshot_bg(snapshot, cell_metrics, self.color, pos, len),
+            RenderStepKind::Background(blend) =>
+                snapshot_bg(snapshot, cell_metrics, self.color, blend, pos, len),
             RenderStepKind::Strikethrough =>
                 snapshot_strikethrough(snapshot, cell_metrics, self.color, pos, len),
             RenderStepKind::Underline =>
@@ -56,6 +60,8 @@ impl<'a> RenderStep<'a> {
                 snapshot_underdot(snapshot, cell_metrics, self.color, pos, len),
             RenderStepKind::Underdash =>
                 snapshot_underdash(snapshot, cell_metrics, self.color, pos, len),
+            RenderStepKind::Undercurl =>
+                snapshot_undercurl(snapshot, cell_metrics, self.color, pos, len),
         }
     }
 
@@ -72,19 +78,24 @@ impl<'a> RenderStep<'a> {
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum RenderStepKind {
-    Background,
+    /// A background, along with its blend value
+    Background(u8),
     Underline,
     Underdouble,
     Underdot,
     Underdash,
+    Undercurl,
     Strikethrough,
 }
 
+/// Create a render node for a line. If `opaque` is set, cells with the default background have
+/// their background drawn too, which is needed for grids drawn on top of other grids. The node is
+/// positioned relative to the top of the line, so it stays valid when the line gets scrolled.
 pub fn snapshot_nvim_line(
     font_ctx: &Context,
     line: &ui_model::Line,
-    row: usize,
     hl: &HighlightMap,
+    opaque: bool,
 ) -> Option<gsk::RenderNode> {
     let snapshot = gtk::Snapshot::new();
     let cell_metrics = font_ctx.cell_metrics();
@@ -110,7 +121,8 @@ pub fn snapshot_nvim_line(
             hl,
             cell,
             cell_metrics,
-            (row, col),
+            (0, col),
+            opaque,
         );
         plan_underline_strikethrough(
             &mut pending_strikethrough,
@@ -119,7 +131,7 @@ pub fn snapshot_nvim_line(
             &mut text_fmt_steps,
             hl,
             cell,
-            (row, col),
+            (0, col),
         );
     }
 
@@ -133,7 +145,7 @@ pub fn snapshot_nvim_line(
             &line.item_line[col],
             hl,
             cell,
-            (row, col),
+            (0, col),
             cell_metrics,
         );
     }
@@ -153,15 +165,30 @@ pub fn snapshot_nvim(
     // Some code paths, such as the cmdline viewport, still consume a full-grid snapshot.
     let snapshot = gtk::Snapshot::new();
 
+    let line_height = font_ctx.cell_metrics().line_height;
     for (row, line) in ui_model.model().iter().enumerate() {
-        if let Some(line_snapshot) = snapshot_nvim_line(font_ctx, line, row, hl) {
-            snapshot.append_node(&line_snapshot);
+        if let Some(line_snapshot) = snapshot_nvim_line(font_ctx, line, hl, false) {
+            append_nvim_line(&snapshot, &line_snapshot, row as f64, line_height);
         }
     }
 
     snapshot.to_node()
 }
 
+/// Append the render node of a line from [`snapshot_nvim_line`] at `row`, which can be fractional
+/// while the line is being scrolled
+pub fn append_nvim_line(
+    snapshot: &gtk::Snapshot,
+    node: &gsk::RenderNode,
+    row: f64,
+    line_height: f64,
+) {
+    snapshot.save();
+    snapshot.translate(&Point::new(0.0, (row * line_height) as f32));
+    snapshot.append_node(node);
+    snapshot.restore();
+}
+
 pub fn snapshot_cursor<T: CursorRedrawCb + 'static>(
     snapshot: &gtk::Snapshot,
     cursor: &Cursor<T>,
@@ -169,6 +196,8 @@ pub fn snapshot_cursor<T: CursorRedrawCb + 'static>(
     ui_model: &ui_model::UiModel,
     hl: &HighlightMap,
     transparency: TransparencySettings,
+    origin: (f64, f64),
+    now: Option<i64>,
 ) {
     if !cursor.is_visible() {
         return;
@@ -200,6 +229,12 @@ pub fn snapshot_cursor<T: CursorRedrawCb + 'static>(
         clip_height as f32,
     );
 
+    // The cursor moves between grids, so it is animated in viewport coordinates
+    let (origin_x, origin_y) = (origin.0 as f32, origin.1 as f32);
+    let cursor_rect = cursor
+        .move_to(clip_rect.offset_r(origin_x, origin_y), now)
+        .offset_r(-origin_x, -origin_y);
+
     let bg_alpha = transparency.background_alpha;
     let filled_alpha = transparency.filled_alpha;
     let alpha = bg_alpha + ((filled_alpha - bg_alpha) * fade_percentage);
@@ -208,24 +243,26 @@ pub fn snapshot_cursor<T: CursorRedrawCb + 'static>(
         snapshot.append_color(&gdk::RGBA::new(0.0, 0.0, 0.0, 0.0), &clip_rect);
     }
 
-    cursor.snapshot(
-        snapshot,
-        font_ctx,
-        (x, y),
-        cell,
-        double_width,
-        hl,
-        fade_percentage,
-        alpha,
-    );
+    let (cursor_bg, cursor_fg) = hl.cursor_colors(cursor.attr_id(), cell);
+    if let Some(now) = now {
+        for (rect, trail_alpha) in cursor.trail(now) {
+            snapshot.append_color(
+                &cursor_bg.to_rgbo(alpha * trail_alpha),
+                &rect.offset_r(-origin_x, -origin_y),
+            );
+        }
+    }
 
-    // Skip re-rendering cell if it isn't needed
-    if !is_focused {
+    cursor.snapshot(snapshot, &cursor_rect, cell, hl, fade_percentage, alpha);
+
+    // Skip re-rendering cell if it isn't needed. The text only gets drawn under the cursor once
+    // it has settled.
+    if !is_focused || cursor.is_moving() {
         return;
     }
 
     let cell_start_col = cursor_line.cell_to_item(cursor_col);
-    let fg = hl.actual_cell_fg(cell).fade(hl.bg(), fade_percentage);
+    let fg = hl.actual_cell_fg(cell).fade(&cursor_fg, fade_percentage);
 
     if cell_start_col >= 0 {
         snapshot.push_clip(&clip_rect);
@@ -243,47 +280,109 @@ pub fn snapshot_cursor<T: CursorRedrawCb + 'static>(
         snapshot.pop();
     }
 
-    if cell.hl.strikethrough {
+    let cell_hl = hl.cell_hl(cell);
+    if cell_hl.strikethrough {
         snapshot_strikethrough(snapshot, cell_metrics, &fg, (x, y), clip_width);
     }
 
-    if cell.hl.underdashed {
+    if cell_hl.underdashed {
         snapshot_underdash(
             snapshot,
             cell_metrics,
-            &underline_color(cell, hl).fade(hl.bg(), fade_percentage),
+            &underline_color(cell, hl).fade(&cursor_fg, fade_percentage),
             (x, y),
             clip_width,
         );
-    } else if cell.hl.underdotted {
+    } else if cell_hl.underdotted {
         snapshot_underdot(
             snapshot,
             cell_metrics,
-            &underdotted_color(cell, hl).fade(hl.bg(), fade_percentage),
+            &special_color(cell, hl).fade(&cursor_fg, fade_percentage),
+            (x, y),
+            clip_width,
+        );
+    } else if cell_hl.undercurl {
+        snapshot_undercurl(
+            snapshot,
+            cell_metrics,
+            &special_color(cell, hl).fade(&cursor_fg, fade_percentage),
             (x, y),
             clip_width,
         );
-    } else if cell.hl.underline {
+    } else if cell_hl.underline {
         snapshot_underline(
             snapshot,
             cell_metrics,
-            &underline_color(cell, hl).fade(hl.bg(), fade_percentage),
+            &underline_color(cell, hl).fade(&cursor_fg, fade_percentage),
             (x, y),
             clip_width,
         );
     }
 
-    if cell.hl.underdouble {
+    if cell_hl.underdouble {
         snapshot_underdouble(
             snapshot,
             cell_metrics,
-            &underline_color(cell, hl).fade(hl.bg(), fade_percentage),
+            &underline_color(cell, hl).fade(&cursor_fg, fade_percentage),
             (x, y),
             clip_width,
         );
     }
 }
 
+/// Draw the preedit string of an input method over the cells at the cursor, with a caret
+pub fn snapshot_preedit(
+    snapshot: &gtk::Snapshot,
+    preedit: &Preedit,
+    font_ctx: &Context,
+    ui_model: &ui_model::UiModel,
+    hl: &HighlightMap,
+) {
+    let cell_metrics = font_ctx.cell_metrics();
+    let (x, y) = cell_metrics.get_pixel_coords(ui_model.get_flushed_cursor());
+
+    // Input methods usually only style parts of the string, so start off with the default colors
+    let attrs = pango::AttrList::new();
+    attrs.insert(hl.fg().to_pango_fg());
+    for attr in preedit.attrs.attributes() {
+        attrs.insert(attr);
+    }
+    let layout = font_ctx.create_layout();
+    layout.set_text(&preedit.text);
+    layout.set_attributes(Some(&attrs));
+
+    let (_, logical_rect) = layout.pixel_extents();
+    snapshot.append_color(
+        &hl.bg().to_rgbo(1.0),
+        &Rect::new(
+            x as f32,
+            y as f32,
+            (logical_rect.width() as f64).max(cell_metrics.char_width) as f32,
+            cell_metrics.line_height as f32,
+        ),
+    );
+
+    let baseline = f64::from(layout.baseline()) / f64::from(pango::SCALE);
+    snapshot.save();
+    snapshot.translate(&Point::new(
+        x as f32,
+        (y + cell_metrics.ascent - baseline) as f32,
+    ));
+    snapshot.append_layout(&layout, &hl.fg().to_rgbo(1.0));
+    snapshot.restore();
+
+    let (caret, _) = layout.cursor_pos(preedit.caret as i32);
+    snapshot.append_color(
+        &hl.fg().to_rgbo(1.0),
+        &Rect::new(
+            (x + f64::from(caret.x()) / f64::from(pango::SCALE)) as f32,
+            y as f32,
+            1.0,
+            cell_metrics.line_height as f32,
+        ),
+    );
+}
+
 fn snapshot_strikethrough(
     snapshot: &gtk::Snapshot,
     cell_metrics: &CellMetrics,
@@ -436,6 +535,67 @@ fn snapshot_underdash(
     snapshot.pop();
 }
 
+fn snapshot_undercurl(
+    snapshot: &gtk::Snapshot,
+    cell_metrics: &CellMetrics,
+    color: &color::Color,
+    (x, y): (f64, f64),
+    len: f64,
+) {
+    let CellMetrics {
+        underline_position,
+        underline_thickness,
+        line_height,
+        char_width,
+        ..
+    } = *cell_metrics;
+
+    /* The wave is drawn in the descent area between the underline position and the bottom of the
+     * line, and as with snapshot_underdot() each period starts on an X coordinate that's a multiple
+     * of the period so that waves of different colors line up with each other.
+     */
+    let thickness = underline_thickness.max(1.0);
+    let top = (y + underline_position).floor();
+    let amplitude =
+        ((y + line_height - top - thickness) / 2.0).clamp(thickness / 2.0, thickness * 2.0);
+    let center = top + thickness / 2.0 + amplitude;
+    let half_period = (char_width / 2.0).round().max(thickness * 2.0);
+    let start_x = x - (x % (half_period * 2.0));
+
+    let cr = snapshot.append_cairo(&Rect::new(
+        x as f32,
+        top as f32,
+        len as f32,
+        (amplitude * 2.0 + thickness) as f32,
+    ));
+    cr.set_source_rgb(color.0, color.1, color.2);
+    cr.set_line_width(thickness);
+
+    /* Each half of a period is a cubic bézier approximating a sine wave, since the control points
+     * are 4/3 of the amplitude away the curve peaks right at the amplitude.
+     */
+    let control = amplitude * 4.0 / 3.0;
+    let mut direction = -1.0;
+    let mut curve_x = start_x;
+    cr.move_to(curve_x, center);
+    while curve_x < x + len {
+        cr.curve_to(
+            curve_x + half_period / 3.0,
+            center + control * direction,
+            curve_x + half_period * 2.0 / 3.0,
+            center + control * direction,
+            curve_x + half_period,
+            center,
+        );
+        curve_x += half_period;
+        direction = -direction;
+    }
+
+    if let Err(err) = cr.stroke() {
+        warn!("Failed to draw undercurl: {err}");
+    }
+}
+
 fn plan_and_snapshot_cell_bg<'a>(
     snapshot: &gtk::Snapshot,
     pending_bg: &mut Option<RenderStep<'a>>,
@@ -443,19 +603,23 @@ fn plan_and_snapshot_cell_bg<'a>(
     cell: &'a ui_model::Cell,
     cell_metrics: &CellMetrics,
     (row, col): (usize, usize),
+    opaque: bool,
 ) {
-    if let Some(cell_bg) = hl.cell_bg(cell).filter(|bg| *bg != hl.bg()) {
+    let cell_bg = if opaque {
+        Some(hl.actual_cell_bg(cell))
+    } else {
+        hl.cell_bg(cell).filter(|bg| *bg != hl.bg())
+    };
+
+    if let Some(cell_bg) = cell_bg {
+        let kind = RenderStepKind::Background(hl.cell_hl(cell).blend);
         if let Some(cur_pending_bg) = pending_bg {
-            if cur_pending_bg.extend(RenderStepKind::Background, cell_bg) {
+            if cur_pending_bg.extend(kind, cell_bg) {
                 return;
             }
             cur_pending_bg.to_snapshot(snapshot, cell_metrics);
         }
-        *pending_bg = Some(RenderStep::new(
-            RenderStepKind::Background,
-            cell_bg,
-            (row, col),
-        ));
+        *pending_bg = Some(RenderStep::new(kind, cell_bg, (row, col)));
     } else if let Some(pending_bg) = pending_bg.take() {
         pending_bg.to_snapshot(snapshot, cell_metrics);
     }
@@ -465,7 +629,8 @@ fn underline_color<'a>(cell: &'a ui_model::Cell, hl: &'a HighlightMap) -> &'a co
     hl.cell_sp(cell).unwrap_or_else(|| hl.actual_cell_fg(cell))
 }
 
-fn underdotted_color<'a>(cell: &'a ui_model::Cell, hl: &'a HighlightMap) -> &'a color::Color {
+/// Underdots and undercurls are drawn in red if there's no special color, like in Vim
+fn special_color<'a>(cell: &'a ui_model::Cell, hl: &'a HighlightMap) -> &'a color::Color {
     hl.cell_sp(cell).unwrap_or(&color::COLOR_RED)
 }
 
@@ -478,7 +643,8 @@ fn plan_underline_strikethrough<'a>(
     cell: &'a ui_model::Cell,
     pos: (usize, usize),
 ) {
-    if cell.hl.strikethrough {
+    let cell_hl = hl.cell_hl(cell);
+    if cell_hl.strikethrough {
         let fg = hl.actual_cell_fg(cell);
         let mut extended = false;
         if let Some(idx) = *pending_strikethrough {
@@ -493,11 +659,13 @@ fn plan_underline_strikethrough<'a>(
         *pending_strikethrough = None;
     }
 
-    let (kind, color) = if cell.hl.underdashed {
+    let (kind, color) = if cell_hl.underdashed {
         (RenderStepKind::Underdash, underline_color(cell, hl))
-    } else if cell.hl.underdotted {
-        (RenderStepKind::Underdot, underdotted_color(cell, hl))
-    } else if cell.hl.underline {
+    } else if cell_hl.underdotted {
+        (RenderStepKind::Underdot, special_color(cell, hl))
+    } else if cell_hl.undercurl {
+        (RenderStepKind::Undercurl, special_color(cell, hl))
+    } else if cell_hl.underline {
         (RenderStepKind::Underline, underline_color(cell, hl))
     } else {
         *pending_underline = None;
@@ -515,7 +683,7 @@ fn plan_underline_strikethrough<'a>(
         pending_fmt_ops.push(RenderStep::new(kind, color, pos));
     }
 
-    if cell.hl.underdouble {
+    if cell_hl.underdouble {
         if let Some(idx) = *pending_underdouble
             && pending_fmt_ops[idx].extend(RenderStepKind::Underdouble, color)
         {
@@ -531,11 +699,13 @@ fn snapshot_bg(
     snapshot: &gtk::Snapshot,
     cell_metrics: &CellMetrics,
     color: &color::Color,
+    blend: u8,
     (x, y): (f64, f64),
     len: f64,
 ) {
+    // Any transparency from TransparencySettings is applied on top of this by the viewport
     snapshot.append_color(
-        &color.into(),
+        &color.to_rgbo(1.0 - f64::from(blend) / 100.0),
         &Rect::new(
             x as f32,
             y as f32,
@@ -566,7 +736,12 @@ fn snapshot_cell(
     }
 }
 
+/// How many shape cache lookups to do between logging how well the cache is doing
+const SHAPE_STATS_INTERVAL: u64 = 10_000;
+
 pub fn shape_dirty(ctx: &context::Context, ui_model: &mut ui_model::UiModel, hl: &HighlightMap) {
+    let lookups_before = ctx.shape_cache_stats().lookups();
+
     for line in ui_model.model_mut() {
         if !line.dirty_line {
             continue;
@@ -579,17 +754,15 @@ pub fn shape_dirty(ctx: &context::Context, ui_model: &mut ui_model::UiModel, hl:
         for (col, cell) in line.line.iter_mut().enumerate() {
             if cell.dirty {
                 for item in &mut *line.item_line[col] {
-                    let mut glyphs = pango::GlyphString::new();
-                    {
-                        let analysis = item.analysis();
-                        let offset = item.item.offset() as usize;
-                        let length = item.item.length() as usize;
-                        if let Some(line_str) = styled_line.line_str.get(offset..offset + length) {
-                            pango::shape(line_str, analysis, &mut glyphs);
-                        } else {
+                    let offset = item.item.offset() as usize;
+                    let length = item.item.length() as usize;
+                    let glyphs = match styled_line.line_str.get(offset..offset + length) {
+                        Some(line_str) => ctx.shape(line_str, item.analysis()),
+                        None => {
                             warn!("Wrong itemize split");
+                            pango::GlyphString::new()
                         }
-                    }
+                    };
 
                     item.set_glyphs(glyphs);
                 }
@@ -600,4 +773,85 @@ pub fn shape_dirty(ctx: &context::Context, ui_model: &mut ui_model::UiModel, hl:
 
         line.dirty_line = false;
     }
+
+    let stats = ctx.shape_cache_stats();
+    if stats.lookups() / SHAPE_STATS_INTERVAL != lookups_before / SHAPE_STATS_INTERVAL {
+        debug!(
+            "Shape cache: {} hits, {} misses, {} evictions ({:.1}% hit rate)",
+            stats.hits,
+            stats.misses,
+            stats.evictions,
+            stats.hit_rate() * 100.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROWS: u64 = 60;
+    const COLUMNS: u64 = 200;
+    const SCROLLS: usize = 1_000;
+
+    /// Scroll the whole model up by a row the way `UiModel::scroll()` did before it moved whole
+    /// lines around, copying the cells of every row into the one above it
+    fn scroll_by_copying_cells(model: &mut ui_model::UiModel) {
+        let columns = model.columns;
+        let lines = model.model_mut();
+        for row in 1..lines.len() {
+            let (above, below) = lines.split_at_mut(row);
+            below[0].copy_to(&mut above[row - 1], 0, columns - 1);
+        }
+        lines.last_mut().unwrap().clear(0, columns - 1);
+    }
+
+    /// Compares the work `shape_dirty()` does after scrolling with whole lines being moved, against
+    /// the cells being copied like before. Needs a display for the pango context, run with
+    /// `cargo test --release bench_scroll_shape_dirty -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_scroll_shape_dirty() {
+        gtk::init().unwrap();
+        let pango_context = gtk::Label::new(None).create_pango_context();
+        pango_context.set_font_description(&pango::FontDescription::from_string("Monospace 12"));
+        let hl = HighlightMap::new();
+        let text = "fn main() { println!(\"Hello, world!\"); } ".repeat(COLUMNS as usize);
+        let put_line = |model: &mut ui_model::UiModel, row: usize, skip: usize| {
+            for (col, ch) in text.chars().skip(skip).take(COLUMNS as usize).enumerate() {
+                model.put_one(row, col, ch.encode_utf8(&mut [0; 4]), false, 0);
+            }
+        };
+
+        let run = |scroll: fn(&mut ui_model::UiModel)| {
+            let ctx = Context::new(pango_context.clone());
+            let mut model = ui_model::UiModel::new(ROWS, COLUMNS);
+            for row in 0..ROWS as usize {
+                put_line(&mut model, row, row % 40);
+            }
+            shape_dirty(&ctx, &mut model, &hl);
+            let initial = ctx.shape_cache_stats();
+
+            let start = std::time::Instant::now();
+            for i in 0..SCROLLS {
+                scroll(&mut model);
+                put_line(&mut model, ROWS as usize - 1, i % 40);
+                shape_dirty(&ctx, &mut model, &hl);
+            }
+            let elapsed = start.elapsed();
+
+            let stats = ctx.shape_cache_stats();
+            let calls = stats.lookups() - initial.lookups();
+            (calls, stats.misses - initial.misses, elapsed)
+        };
+
+        let (moved, moved_misses, moved_time) =
+            run(|model| model.scroll(0, ROWS as i64 - 1, 0, COLUMNS as usize - 1, 1));
+        let (copied, copied_misses, copied_time) = run(scroll_by_copying_cells);
+        println!("moving lines: shaped {moved} runs ({moved_misses} not cached) in {moved_time:?}");
+        println!(
+            "copying cells: shaped {copied} runs ({copied_misses} not cached) in {copied_time:?}"
+        );
+        assert!(moved * 10 < copied);
+    }
 }
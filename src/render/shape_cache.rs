@@ -0,0 +1,252 @@
+use std::hash::Hash;
+
+use fnv::FnvHashMap;
+
+/// How many shaped runs to keep around. A full screen of code is usually a few thousand runs, so
+/// this is enough to cover redrawing the screen along with the runs that scrolled out of it.
+const SHAPE_CACHE_CAPACITY: usize = 8192;
+
+/// Index used to mark the ends of the recently used list
+const NIL: usize = usize::MAX;
+
+/// Hit and miss counts of a cache since it was last cleared
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.lookups();
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    prev: usize,
+    next: usize,
+}
+
+/// A map holding at most `capacity` entries, evicting the least recently used one when full
+pub struct LruCache<K, V> {
+    map: FnvHashMap<K, usize>,
+    entries: Vec<Entry<K, V>>,
+    /// The most recently used entry
+    head: usize,
+    /// The least recently used entry
+    tail: usize,
+    capacity: usize,
+    stats: CacheStats,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        debug_assert!(capacity > 0);
+
+        LruCache {
+            map: FnvHashMap::default(),
+            entries: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            capacity,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+        self.head = NIL;
+        self.tail = NIL;
+        self.stats = CacheStats::default();
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        match self.map.get(key) {
+            Some(&idx) => {
+                self.stats.hits += 1;
+                self.unlink(idx);
+                self.push_front(idx);
+                Some(&self.entries[idx].value)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(&idx) = self.map.get(&key) {
+            self.entries[idx].value = value;
+            self.unlink(idx);
+            self.push_front(idx);
+            return;
+        }
+
+        let idx = if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                key: key.clone(),
+                value,
+                prev: NIL,
+                next: NIL,
+            });
+            self.entries.len() - 1
+        } else {
+            // Reuse the slot of the least recently used entry
+            let idx = self.tail;
+            self.unlink(idx);
+            let entry = &mut self.entries[idx];
+            self.map.remove(&entry.key);
+            entry.key = key.clone();
+            entry.value = value;
+            self.stats.evictions += 1;
+            idx
+        };
+
+        self.map.insert(key, idx);
+        self.push_front(idx);
+    }
+
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = (self.entries[idx].prev, self.entries[idx].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, idx: usize) {
+        self.entries[idx].prev = NIL;
+        self.entries[idx].next = self.head;
+        match self.head {
+            NIL => self.tail = idx,
+            head => self.entries[head].prev = idx,
+        }
+        self.head = idx;
+    }
+}
+
+/// What a run of text gets shaped with. Font features and line spacing apply to every run the
+/// same way, so instead of being part of the key the cache gets cleared when they change.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ShapeKey {
+    text: Box<str>,
+    font: pango::Font,
+    /// The bidi level, as right to left text gets shaped in reverse
+    level: u8,
+    /// Script, language and gravity pick the shaping rules and glyph variants for the run
+    script: pango::Script,
+    language: pango::Language,
+    gravity: pango::Gravity,
+}
+
+/// Glyphs of recently shaped runs of text, so redrawing a line that didn't change much (typing,
+/// a statusline or a blinking UI) doesn't need Pango to shape all of it again
+pub struct ShapeCache {
+    cache: LruCache<ShapeKey, pango::GlyphString>,
+}
+
+impl ShapeCache {
+    pub fn new() -> Self {
+        ShapeCache {
+            cache: LruCache::new(SHAPE_CACHE_CAPACITY),
+        }
+    }
+
+    pub fn shape(&mut self, text: &str, analysis: &pango::Analysis) -> pango::GlyphString {
+        let key = ShapeKey {
+            text: text.into(),
+            font: analysis.font(),
+            level: analysis.level(),
+            script: analysis.script(),
+            language: analysis.language(),
+            gravity: analysis.gravity(),
+        };
+        if let Some(glyphs) = self.cache.get(&key) {
+            return glyphs.clone();
+        }
+
+        let mut glyphs = pango::GlyphString::new();
+        pango::shape(text, analysis, &mut glyphs);
+        self.cache.insert(key, glyphs.clone());
+        glyphs
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(Some(&1), cache.get(&"a"));
+
+        // "b" is now the least recently used
+        cache.insert("c", 3);
+        assert_eq!(2, cache.len());
+        assert_eq!(None, cache.get(&"b"));
+        assert_eq!(Some(&1), cache.get(&"a"));
+        assert_eq!(Some(&3), cache.get(&"c"));
+
+        cache.insert("a", 4);
+        cache.insert("d", 5);
+        assert_eq!(None, cache.get(&"c"));
+        assert_eq!(Some(&4), cache.get(&"a"));
+        assert_eq!(Some(&5), cache.get(&"d"));
+
+        assert_eq!(
+            CacheStats {
+                hits: 5,
+                misses: 2,
+                evictions: 2,
+            },
+            cache.stats()
+        );
+        assert_eq!(5.0 / 7.0, cache.stats().hit_rate());
+
+        cache.clear();
+        assert_eq!(0, cache.len());
+        assert_eq!(None, cache.get(&"a"));
+        assert_eq!(1, cache.stats().misses);
+    }
+}
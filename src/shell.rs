@@ -5,7 +5,7 @@ use std::ops::Deref;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, mpsc};
-use std::{env, thread};
+use std::{env, mem, thread};
 
 use log::{debug, error};
 
@@ -22,27 +22,31 @@ use pango::FontDescription;
 use nvim_rs::Value;
 
 use crate::color::{COLOR_BLACK, COLOR_WHITE, Color};
-use crate::grid::GridMap;
+use crate::grid::{DEFAULT_GRID, FloatAnchor, Grid, GridMap, GridViewport};
 use crate::highlight::{BackgroundState, HighlightMap};
 use crate::misc::{decode_uri, escape_filename, split_at_comma};
 use crate::nvim::{
-    self, CallErrorExt, ErrorReport, NeovimApiInfo, NeovimClient, NormalError, NvimHandler,
-    NvimInitError, NvimSession, PendingPopupMenu, RedrawMode, Tabpage,
+    self, CallErrorExt, ErrorReport, GridLineCell, MouseInput, NeovimApiInfo, NeovimClient,
+    NormalError, NvimHandler, NvimInitError, NvimSession, PendingPopupMenu, RedrawMode, Tabpage,
 };
 use crate::settings::{FontSource, Settings};
-use crate::ui_model::ModelRect;
+use crate::ui_model::{Link, ModelRect};
 use crate::{NvimTransport, spawn_timeout, spawn_timeout_user_err};
 
 use crate::Args;
 use crate::cmd_line::{CmdLine, CmdLineContext};
-use crate::cursor::{Cursor, CursorRedrawCb};
+use crate::cursor::{Cursor, CursorAnimation, CursorRedrawCb};
 use crate::input;
 use crate::input::keyval_to_input_string;
+use crate::messages::{Message, MessageArea, StatusStrip};
 use crate::mode;
+use crate::nvim_menu::{self, MenuBuilder, MenuCommand};
 use crate::nvim_viewport::NvimViewport;
+use crate::pointer::{Hover, Pointer, mouse_shape_name};
 use crate::popup_menu::PopupMenu;
 use crate::render;
 use crate::render::CellMetrics;
+use crate::shell_dlg::{self, Prompt};
 use crate::subscriptions::{SubscriptionHandle, SubscriptionKey, Subscriptions};
 use crate::tabline::Tabline;
 use crate::ui::{Components, UiMutex};
@@ -50,6 +54,9 @@ use crate::ui::{Components, UiMutex};
 const DEFAULT_FONT_NAME: &str = "DejaVu Sans Mono 12";
 pub const MINIMUM_SUPPORTED_NVIM_VERSION: &str = "0.3.2";
 
+/// The id of the notification sent when the bell rings in a window that isn't focused
+const BELL_NOTIFICATION: &str = "bell";
+
 macro_rules! idle_cb_call {
     ($state:ident.$cb:ident($( $x:expr ),*)) => (
         glib::idle_add_once(move || {
@@ -98,6 +105,22 @@ impl Default for TransparencySettings {
     }
 }
 
+/// How floating windows are drawn on top of the grids below them
+#[derive(Clone, Copy)]
+pub struct FloatStyle {
+    pub shadow: bool,
+    pub corner_radius: f32,
+}
+
+impl Default for FloatStyle {
+    fn default() -> Self {
+        FloatStyle {
+            shadow: true,
+            corner_radius: 6.0,
+        }
+    }
+}
+
 /// Contains state related to resize requests we are going to/have sent to nvim
 pub struct ResizeRequests {
     /// The most recently submitted resize request, if any. This might not have been received by
@@ -129,6 +152,7 @@ pub struct HeaderBarButtons {
     paste_btn: Button,
     save_btn: Button,
     primary_menu_btn: MenuButton,
+    messages_btn: MenuButton,
 }
 
 impl HeaderBarButtons {
@@ -138,6 +162,7 @@ impl HeaderBarButtons {
         paste_btn: Button,
         save_btn: Button,
         primary_menu_btn: MenuButton,
+        messages_btn: MenuButton,
     ) -> Self {
         Self {
             open_btn,
@@ -145,9 +170,15 @@ impl HeaderBarButtons {
             paste_btn,
             primary_menu_btn,
             save_btn,
+            messages_btn,
         }
     }
 
+    /// The message history only gets filled in when Neovim sends us its messages
+    pub fn set_messages_visible(&self, visible: bool) {
+        self.messages_btn.set_visible(visible);
+    }
+
     pub fn set_enabled(&self, enabled: bool) {
         self.new_tab_btn.set_sensitive(enabled);
         self.paste_btn.set_sensitive(enabled);
@@ -179,6 +210,35 @@ impl ActionWidgets {
         self.tabs.set_sensitive(enabled);
         self.file_browser.set_sensitive(enabled);
     }
+
+    pub fn set_messages_visible(&self, visible: bool) {
+        if let Some(ref header_bar) = self.header_bar {
+            header_bar.set_messages_visible(visible);
+        }
+    }
+}
+
+/// The string an input method is composing, shown at the cursor until it gets committed
+pub struct Preedit {
+    pub text: glib::GString,
+    pub attrs: pango::AttrList,
+    /// The position of the caret in `text`, in bytes
+    pub caret: usize,
+}
+
+impl Preedit {
+    /// Create a preedit from `IMContext::preedit_string()`, which gives the caret in characters
+    fn new((text, attrs, caret): (glib::GString, pango::AttrList, i32)) -> Option<Self> {
+        if text.is_empty() {
+            return None;
+        }
+
+        let caret = text
+            .char_indices()
+            .nth(caret.max(0) as usize)
+            .map_or(text.len(), |(idx, _)| idx);
+        Some(Preedit { text, attrs, caret })
+    }
 }
 
 type CommandCallback = Box<dyn FnMut(&mut State, nvim::NvimCommand) + Send + 'static>;
@@ -189,6 +249,17 @@ pub struct State {
     pub grids: GridMap,
 
     mouse_enabled: bool,
+    /// Whether to hide the pointer while typing, from 'mousehide'
+    mouse_hide: bool,
+    /// Whether to send mouse move events, from 'mousemoveevent'
+    mouse_move_event: bool,
+    /// Whether right clicks open our own context menu instead of going to Neovim
+    context_menu: bool,
+    /// The commands of the items in the context menu, from Neovim's PopUp menu
+    popup_commands: Rc<RefCell<Vec<MenuCommand>>>,
+    /// Whether shortcuts use the keys of a Latin layout when typing on a non-Latin one
+    keycode_fallback: bool,
+    pointer: RefCell<Pointer>,
     nvim: Rc<NeovimClient>,
     cursor: Option<Cursor<State>>,
     popup_menu: PopupMenu,
@@ -206,12 +277,21 @@ pub struct State {
     pub nvim_viewport: NvimViewport,
     pub pending_redraw: RedrawMode,
     pub pending_popupmenu: PendingPopupMenu,
+    /// Redraws and popup menu changes waiting for the next frame
+    frame_redraw: RedrawMode,
+    frame_popupmenu: PendingPopupMenu,
     tabs: Tabline,
     im_context: gtk::IMMulticontext,
+    preedit: Option<Preedit>,
     error_area: crate::error::ErrorArea,
+    messages: MessageArea,
+    status_strip: StatusStrip,
+    prompt_dlg: Option<shell_dlg::PromptDlg>,
 
     pub options: RefCell<Args>,
     transparency_settings: TransparencySettings,
+    float_style: FloatStyle,
+    smooth_scroll: bool,
 
     detach_cb: Option<DetachedCallback>,
     nvim_started_cb: Option<NvimStartedCallback>,
@@ -245,6 +325,12 @@ impl State {
             grids: GridMap::new(),
             nvim: Rc::new(NeovimClient::new()),
             mouse_enabled: true,
+            mouse_hide: true,
+            mouse_move_event: false,
+            context_menu: false,
+            keycode_fallback: true,
+            popup_commands: Rc::new(RefCell::new(Vec::new())),
+            pointer: RefCell::new(Pointer::new()),
             cursor: None,
             popup_menu,
             cmd_line,
@@ -273,12 +359,20 @@ impl State {
             nvim_viewport,
             pending_redraw: RedrawMode::Nothing,
             pending_popupmenu: PendingPopupMenu::None,
+            frame_redraw: RedrawMode::Nothing,
+            frame_popupmenu: PendingPopupMenu::None,
             tabs: Tabline::new(),
             im_context: gtk::IMMulticontext::new(),
+            preedit: None,
             error_area: crate::error::ErrorArea::new(),
+            messages: MessageArea::new(),
+            status_strip: StatusStrip::new(),
+            prompt_dlg: None,
 
             options: RefCell::new(options),
             transparency_settings: TransparencySettings::new(),
+            float_style: FloatStyle::default(),
+            smooth_scroll: false,
 
             detach_cb: None,
             nvim_started_cb: None,
@@ -373,6 +467,7 @@ impl State {
             .borrow_mut()
             .font_ctx
             .update(pango_context);
+        self.update_messages_css();
         self.grids.clear_glyphs();
         self.try_nvim_resize();
         self.queue_draw(RedrawMode::All);
@@ -412,6 +507,8 @@ impl State {
             background_alpha,
             filled_alpha,
         };
+        self.popup_menu
+            .set_transparency(self.transparency_settings, &self.render_state.borrow());
         self.queue_draw(RedrawMode::ClearCache);
     }
 
@@ -419,6 +516,40 @@ impl State {
         self.transparency_settings
     }
 
+    pub fn set_float_style(&mut self, float_style: FloatStyle) {
+        self.float_style = float_style;
+        self.queue_draw(RedrawMode::ClearCache);
+    }
+
+    pub fn float_style(&self) -> FloatStyle {
+        self.float_style
+    }
+
+    pub fn set_context_menu(&mut self, enabled: bool) {
+        self.context_menu = enabled;
+    }
+
+    pub fn set_keycode_fallback(&mut self, enabled: bool) {
+        self.keycode_fallback = enabled;
+    }
+
+    pub fn set_smooth_scroll(&mut self, enabled: bool) {
+        self.smooth_scroll = enabled;
+    }
+
+    pub fn set_cursor_animation(&mut self, animation: CursorAnimation) {
+        if let Some(cursor) = &mut self.cursor {
+            cursor.set_animation(animation);
+        }
+    }
+
+    pub fn set_langmap(&mut self, langmap: bool) {
+        if let Some(cursor) = &mut self.cursor {
+            cursor.set_langmap(langmap);
+        }
+        self.queue_draw(RedrawMode::Cursor);
+    }
+
     pub fn set_cursor_blink(&mut self, val: i32) {
         if let Some(cursor) = &mut self.cursor {
             cursor.set_cursor_blink(val);
@@ -480,13 +611,13 @@ impl State {
         if self.popup_menu.is_open()
             && let Some(nvim) = self.nvim()
         {
-            nvim.block_timeout(nvim.input("<Esc>")).report_err();
+            nvim.queue_input("<Esc>");
         }
     }
 
     fn update_dirty_glyphs(&mut self) {
         let render_state = self.render_state.borrow();
-        if let Some(model) = self.grids.current_model_mut() {
+        for model in self.grids.models_mut() {
             render::shape_dirty(&render_state.font_ctx, model, &render_state.hl);
         }
     }
@@ -519,17 +650,85 @@ impl State {
         glib::idle_add_local_once(move || stack.set_visible_child_name("Error"));
     }
 
-    fn set_im_location(&self) {
-        if let Some((row, col)) = self.grids.current().map(|g| g.get_cursor()) {
-            let (x, y, width, height) = ModelRect::point(col, row)
-                .to_area(self.render_state.borrow().font_ctx.cell_metrics());
+    /// Get the area of the cursor in pixels, relative to the viewport rather than to the grid
+    /// the cursor is in
+    fn cursor_area(&self) -> Option<(i32, i32, i32, i32)> {
+        let grid = self.grids.current()?;
+        let (row, col) = grid.get_cursor();
+        let (grid_row, grid_col) = self.grids.origin(self.grids.current_id());
+        let render_state = self.render_state.borrow();
+        let cell_metrics = render_state.font_ctx.cell_metrics();
+        let (x, y, width, height) = ModelRect::point(col, row).to_area(cell_metrics);
+
+        Some((
+            x + (grid_col * cell_metrics.char_width) as i32,
+            y + (grid_row * cell_metrics.line_height) as i32,
+            width,
+            height,
+        ))
+    }
 
+    fn set_im_location(&self) {
+        if let Some((x, y, width, height)) = self.cursor_area() {
             self.im_context
                 .set_cursor_location(&gdk::Rectangle::new(x, y, width, height));
-            self.im_context.reset();
+            // Redraws can move the cursor while the user is still composing, which shouldn't
+            // throw away the preedit
+            if self.preedit.is_none() {
+                self.im_context.reset();
+            }
         }
     }
 
+    pub fn preedit(&self) -> Option<&Preedit> {
+        self.preedit.as_ref()
+    }
+
+    fn set_preedit(&mut self, preedit: Option<Preedit>) {
+        self.preedit = preedit;
+        self.nvim_viewport.queue_draw();
+    }
+
+    /// The text around the cursor for input methods, and the byte index of the cursor in it. This
+    /// is the screen line of the current grid, which is as close as we can get to the buffer
+    /// text without a round trip to Neovim.
+    fn surrounding_text(&self) -> Option<(String, usize)> {
+        let grid = self.grids.current()?;
+        let (row, col) = grid.get_cursor();
+        let line = grid.model().model().get(row)?;
+
+        let mut text = String::new();
+        let mut cursor = None;
+        for (idx, cell) in line.line.iter().enumerate() {
+            if idx == col {
+                cursor = Some(text.len());
+            }
+            if cell.double_width {
+                continue;
+            }
+            text.push_str(if cell.ch.is_empty() { " " } else { &cell.ch });
+        }
+
+        let cursor = cursor.unwrap_or(text.len());
+        let end = text.trim_end().len().max(cursor);
+        text.truncate(end);
+        Some((text, cursor))
+    }
+
+    /// Delete `n_chars` characters starting `offset` characters away from the cursor, for input
+    /// methods doing reconversion. Only possible in insert mode.
+    fn delete_surrounding(&self, offset: i32, n_chars: i32) -> bool {
+        if !self.render_state.borrow().mode.is(&mode::NvimMode::Insert) {
+            return false;
+        }
+        let Some(nvim) = self.nvim() else {
+            return false;
+        };
+
+        nvim.queue_input(input::delete_surrounding_input(offset, n_chars));
+        true
+    }
+
     pub fn resize_status(&self) -> Arc<ResizeState> {
         self.resize_status.clone()
     }
@@ -570,6 +769,11 @@ impl State {
             status.active = true;
         }
 
+        // With ext_multigrid, the size of the window is the size of the default grid
+        let multigrid = self
+            .nvim
+            .api_info()
+            .is_some_and(|api_info| api_info.ext_multigrid);
         let status_ref = self.resize_status.clone();
         nvim.clone().spawn(async move {
             loop {
@@ -600,10 +804,17 @@ impl State {
                             "au VimResized * ++once cal rpcnotify(1, 'resized')".into(),
                         ]),
                     ]),
-                    Value::Array(vec![
-                        "nvim_ui_try_resize".into(),
-                        Value::Array(vec![cols.into(), rows.into()]),
-                    ]),
+                    if multigrid {
+                        Value::Array(vec![
+                            "nvim_ui_try_resize_grid".into(),
+                            Value::Array(vec![DEFAULT_GRID.into(), cols.into(), rows.into()]),
+                        ])
+                    } else {
+                        Value::Array(vec![
+                            "nvim_ui_try_resize".into(),
+                            Value::Array(vec![cols.into(), rows.into()]),
+                        ])
+                    },
                 ])
                 .await
                 .report_err();
@@ -623,11 +834,54 @@ impl State {
             {
                 spawn_timeout_user_err!(nvim.command(&format!("normal! \"{clipboard}Pl")));
             } else {
-                spawn_timeout_user_err!(nvim.input(&format!("<C-r>{clipboard}")));
+                nvim.queue_input(format!("<C-r>{clipboard}"));
             };
         }
     }
 
+    /// Show the context menu at `(x, y)`, with the PopUp menu from Neovim if it defines one
+    fn show_context_menu(&self, popover: &gtk::PopoverMenu, (x, y): (f64, f64)) {
+        let Some(nvim) = self.nvim() else {
+            return;
+        };
+
+        let popup_commands = self.popup_commands.clone();
+        let popover = popover.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let items = match nvim_menu::menu_get(nvim, "PopUp").await {
+                Ok(items) => items,
+                Err(err) => {
+                    error!("Can't get the PopUp menu: {err}");
+                    Vec::new()
+                }
+            };
+            if items.is_empty() {
+                popup_commands.borrow_mut().clear();
+                popover.set_menu_model(Some(&default_context_menu()));
+            } else {
+                let mut builder = MenuBuilder::new("menu.run");
+                popover.set_menu_model(Some(&builder.build(&items)));
+                *popup_commands.borrow_mut() = builder.into_commands();
+            }
+
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(
+                x.round() as i32,
+                y.round() as i32,
+                0,
+                0,
+            )));
+            popover.popup();
+        });
+    }
+
+    fn run_popup_command(&self, idx: usize) {
+        if let Some(nvim) = self.nvim()
+            && let Some(command) = self.popup_commands.borrow().get(idx)
+        {
+            nvim_menu::run_command(&nvim, command);
+        }
+    }
+
     fn edit_copy(&self, clipboard: &'static str) {
         if let Some(nvim) = self.nvim() {
             spawn_timeout_user_err!(nvim.command(&format!("normal! \"{clipboard}y")));
@@ -732,6 +986,10 @@ impl State {
         self.tabs.set_visible(visible)
     }
 
+    pub fn set_status_strip(&mut self, enabled: bool) {
+        self.status_strip.set_enabled(enabled)
+    }
+
     pub fn set_background(&self, background: BackgroundState) {
         self.render_state
             .borrow_mut()
@@ -742,16 +1000,31 @@ impl State {
     pub fn cursor(&self) -> Option<&Cursor<State>> {
         self.cursor.as_ref()
     }
+
+    fn update_pointer(&self, update: impl FnOnce(&mut Pointer)) {
+        let mut pointer = self.pointer.borrow_mut();
+        update(&mut pointer);
+        pointer.apply(&self.nvim_viewport);
+    }
+
+    /// Hide the pointer while typing, if 'mousehide' is set
+    fn hide_pointer(&self) {
+        if self.mouse_hide {
+            self.update_pointer(|pointer| pointer.hidden = true);
+        }
+    }
 }
 
 pub struct UiState {
-    mouse_pressed: bool,
-    cursor_visible: Option<bool>,
+    /// The mouse button that's currently held down, drag events are sent for it
+    pressed_button: Option<&'static str>,
 
     scroll_delta: (f64, f64),
 
-    /// Last reported editor position (col, row)
-    last_nvim_pos: (u64, u64),
+    /// Last reported editor position (grid, col, row)
+    last_nvim_pos: (u64, u64, u64),
+    /// The grid a mouse button was pressed in, drag and release events are sent to the same grid
+    mouse_grid: Option<u64>,
     /// Last reported motion position
     last_pos: (f64, f64),
 }
@@ -759,30 +1032,23 @@ pub struct UiState {
 impl UiState {
     pub fn new() -> UiState {
         UiState {
-            mouse_pressed: false,
-            cursor_visible: None,
+            pressed_button: None,
             scroll_delta: (0.0, 0.0),
-            last_nvim_pos: (0, 0),
+            last_nvim_pos: (0, 0, 0),
+            mouse_grid: None,
             last_pos: (0.0, 0.0),
         }
     }
+}
 
-    /// Set whether or not the cursor for the drawing area is visible. We cache this in UiState
-    /// since otherwise we'd end up creating a new cursor every single time we receive a motion
-    /// event
-    fn set_cursor_visible(&mut self, nvim_viewport: &NvimViewport, visible: bool) {
-        if Some(visible) == self.cursor_visible {
-            return;
-        }
-
-        self.cursor_visible = Some(visible);
-        let cursor = match visible {
-            true => "text",
-            false => "none",
-        };
-
-        nvim_viewport.set_cursor(gdk::Cursor::from_name(cursor, None).as_ref());
-    }
+/// The context menu we show when Neovim doesn't have a PopUp menu
+fn default_context_menu() -> gio::Menu {
+    let menu = gio::Menu::new();
+    let section = gio::Menu::new();
+    section.append(Some("Copy"), Some("menu.copy"));
+    section.append(Some("Paste"), Some("menu.paste"));
+    menu.append_section(None, &section);
+    menu
 }
 
 async fn gtk_drop_receive(drop: &gdk::Drop) -> Result<Vec<String>, Box<dyn std::error::Error>> {
@@ -925,12 +1191,17 @@ impl Shell {
             .nvim_viewport
             .set_completion_popover(&state.popup_menu);
 
-        state.im_context.set_use_preedit(false);
+        state.im_context.set_use_preedit(true);
 
         let nvim_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
+        // Messages from ext_messages are shown as toasts on top of the viewport
+        let viewport_overlay = gtk::Overlay::builder().child(&state.nvim_viewport).build();
+        viewport_overlay.add_overlay(&*state.messages);
+
         nvim_box.append(&*state.tabs);
-        nvim_box.append(&state.nvim_viewport);
+        nvim_box.append(&viewport_overlay);
+        nvim_box.append(&*state.status_strip);
 
         state.stack.add_named(&nvim_box, Some("Nvim"));
         state.stack.add_named(&*state.error_area, Some("Error"));
@@ -971,19 +1242,20 @@ impl Shell {
         let key_controller = gtk::EventControllerKey::new();
         key_controller.set_im_context(Some(&state.im_context));
         key_controller.connect_key_pressed(glib::clone!(
-            #[strong]
-            ui_state_ref,
             #[weak]
             state_ref,
             #[upgrade_or]
             glib::Propagation::Proceed,
-            move |_, key, _, modifiers| {
+            move |_, key, keycode, modifiers| {
                 let mut state = state_ref.borrow_mut();
                 state.cursor.as_mut().unwrap().reset_state();
-                ui_state_ref
-                    .borrow_mut()
-                    .set_cursor_visible(&state.nvim_viewport, false);
+                state.hide_pointer();
 
+                let key = if state.keycode_fallback {
+                    input::shortcut_keyval(&state.nvim_viewport.display(), key, keycode, modifiers)
+                } else {
+                    key
+                };
                 match state.nvim() {
                     Some(nvim) => input::gtk_key_press(&nvim, key, modifiers),
                     None => glib::Propagation::Proceed,
@@ -1052,15 +1324,17 @@ impl Shell {
             ui_state_ref,
             move |controller, x, y| {
                 let state = state_ref.borrow();
-                gtk_button_press(
+                let modifier_state = controller.current_event_state();
+                gtk_button_press(&state, &ui_state_ref, 3, x, y, modifier_state, &menu);
+                // There's no release for a long press, so finish the right click right away
+                gtk_button_release(
                     &state,
-                    &ui_state_ref,
+                    &mut ui_state_ref.borrow_mut(),
                     3,
                     x,
                     y,
-                    controller.current_event_state(),
-                    &menu,
-                )
+                    modifier_state,
+                );
             }
         ));
         state.nvim_viewport.add_controller(long_tap_controller);
@@ -1086,9 +1360,8 @@ impl Shell {
         ));
         state.nvim_viewport.add_controller(focus_controller);
 
-        let scroll_controller = gtk::EventControllerScroll::new(
-            gtk::EventControllerScrollFlags::BOTH_AXES | gtk::EventControllerScrollFlags::DISCRETE,
-        );
+        let scroll_controller =
+            gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::BOTH_AXES);
         scroll_controller.connect_scroll(glib::clone!(
             #[strong]
             ui_state_ref,
@@ -1097,10 +1370,16 @@ impl Shell {
             #[upgrade_or]
             glib::Propagation::Proceed,
             move |controller, dx, dy| {
+                // Touchpads report smooth scrolls, mouse wheels report discrete steps
+                let precise = controller
+                    .current_event()
+                    .and_then(|event| event.downcast::<gdk::ScrollEvent>().ok())
+                    .is_some_and(|event| event.direction() == gdk::ScrollDirection::Smooth);
                 gtk_scroll_event(
                     &mut state_ref.borrow_mut(),
                     &mut ui_state_ref.borrow_mut(),
                     (dx, dy),
+                    precise,
                     controller.current_event_state(),
                 );
 
@@ -1169,18 +1448,48 @@ impl Shell {
         state.im_context.connect_commit(glib::clone!(
             #[weak]
             state_ref,
-            #[strong]
-            ui_state_ref,
             move |_, ch| {
                 let mut state = state_ref.borrow_mut();
 
                 state.cursor.as_mut().unwrap().reset_state();
-                ui_state_ref
-                    .borrow_mut()
-                    .set_cursor_visible(&state.nvim_viewport, false);
+                state.hide_pointer();
                 state.im_commit(ch);
             }
         ));
+        state.im_context.connect_preedit_changed(glib::clone!(
+            #[weak]
+            state_ref,
+            move |im_context| {
+                state_ref
+                    .borrow_mut()
+                    .set_preedit(Preedit::new(im_context.preedit_string()));
+            }
+        ));
+        state.im_context.connect_preedit_end(glib::clone!(
+            #[weak]
+            state_ref,
+            move |_| state_ref.borrow_mut().set_preedit(None)
+        ));
+        state.im_context.connect_retrieve_surrounding(glib::clone!(
+            #[weak]
+            state_ref,
+            #[upgrade_or]
+            false,
+            move |im_context| match state_ref.borrow().surrounding_text() {
+                Some((text, cursor)) => {
+                    im_context.set_surrounding_with_selection(&text, cursor as i32, cursor as i32);
+                    true
+                }
+                None => false,
+            }
+        ));
+        state.im_context.connect_delete_surrounding(glib::clone!(
+            #[weak]
+            state_ref,
+            #[upgrade_or]
+            false,
+            move |_, offset, n_chars| state_ref.borrow().delete_surrounding(offset, n_chars)
+        ));
 
         state.nvim_viewport.connect_map(glib::clone!(
             #[weak]
@@ -1222,15 +1531,21 @@ impl Shell {
         ));
         action_group.add_action(&paste);
 
-        let menu = gio::Menu::new();
-        let section = gio::Menu::new();
-        section.append(Some("Copy"), Some("menu.copy"));
-        section.append(Some("Paste"), Some("menu.paste"));
-        menu.append_section(None, &section);
+        let run = gio::SimpleAction::new("run", Some(glib::VariantTy::UINT32));
+        run.connect_activate(glib::clone!(
+            #[weak]
+            state_ref,
+            move |_, idx| {
+                if let Some(idx) = idx.and_then(u32::from_variant) {
+                    state_ref.borrow().run_popup_command(idx as usize);
+                }
+            }
+        ));
+        action_group.add_action(&run);
 
         let popover = gtk::PopoverMenu::builder()
             .position(gtk::PositionType::Bottom)
-            .menu_model(&menu)
+            .menu_model(&default_context_menu())
             .has_arrow(false)
             .build();
         popover.insert_action_group("menu", Some(&action_group));
@@ -1347,6 +1662,14 @@ struct FocusState {
 fn gtk_active_notify(state: &mut State, active: bool) {
     state.focus_update(active);
     if active {
+        if let Some(app) = state
+            .nvim_viewport
+            .root()
+            .and_downcast::<gtk::Window>()
+            .and_then(|window| window.application())
+        {
+            app.withdraw_notification(BELL_NOTIFICATION);
+        }
         state.im_context.focus_in();
     } else {
         state.im_context.focus_out();
@@ -1360,6 +1683,7 @@ fn gtk_scroll_event(
     state: &mut State,
     ui_state: &mut UiState,
     (dx, dy): (f64, f64),
+    precise: bool,
     modifier_state: ModifierType,
 ) {
     if !state.mouse_enabled && !state.nvim.is_initializing() {
@@ -1368,6 +1692,22 @@ fn gtk_scroll_event(
 
     state.close_popup_menu();
 
+    // With smooth scrolling, touchpad deltas are counted in lines instead of wheel steps. These
+    // scroll windows directly, which the command line and terminals don't have a use for.
+    let precise = precise
+        && state.smooth_scroll
+        && modifier_state.is_empty()
+        && !state.render_state.borrow().mode.is_cmdline_or_terminal();
+    if precise {
+        scroll_lines(
+            state,
+            ui_state.last_pos,
+            dx * LINES_PER_WHEEL_STEP,
+            dy * LINES_PER_WHEEL_STEP,
+        );
+        return;
+    }
+
     // Remember and accumulate scroll deltas, so slow scrolling still
     // works.
     ui_state.scroll_delta.0 += dx;
@@ -1376,21 +1716,72 @@ fn gtk_scroll_event(
     // Perform scroll action for deltas with abs(delta) >= 1.
     let x = ui_state.scroll_delta.0 as isize;
     let y = ui_state.scroll_delta.1 as isize;
+    // Subtract performed scroll deltas.
+    ui_state.scroll_delta.0 -= x as f64;
+    ui_state.scroll_delta.1 -= y as f64;
+
     for _ in 0..x {
-        mouse_input(state, "wheel", "right", modifier_state, ui_state.last_pos)
+        mouse_input(
+            state,
+            "wheel",
+            "right",
+            modifier_state,
+            ui_state.last_pos,
+            None,
+        )
     }
     for _ in 0..-x {
-        mouse_input(state, "wheel", "left", modifier_state, ui_state.last_pos)
+        mouse_input(
+            state,
+            "wheel",
+            "left",
+            modifier_state,
+            ui_state.last_pos,
+            None,
+        )
     }
     for _ in 0..y {
-        mouse_input(state, "wheel", "down", modifier_state, ui_state.last_pos)
+        mouse_input(
+            state,
+            "wheel",
+            "down",
+            modifier_state,
+            ui_state.last_pos,
+            None,
+        )
     }
     for _ in 0..-y {
-        mouse_input(state, "wheel", "up", modifier_state, ui_state.last_pos)
+        mouse_input(
+            state,
+            "wheel",
+            "up",
+            modifier_state,
+            ui_state.last_pos,
+            None,
+        )
     }
-    // Subtract performed scroll deltas.
-    ui_state.scroll_delta.0 -= x as f64;
-    ui_state.scroll_delta.1 -= y as f64;
+}
+
+/// How many lines Neovim scrolls per wheel step with the default 'mousescroll'
+const LINES_PER_WHEEL_STEP: f64 = 3.0;
+
+fn scroll_lines(shell: &State, position: (f64, f64), columns: f64, lines: f64) {
+    let Some(nvim) = shell.nvim() else {
+        return;
+    };
+    let &CellMetrics {
+        line_height,
+        char_width,
+        ..
+    } = shell.render_state.borrow().font_ctx.cell_metrics();
+    let (x, y) = position;
+
+    nvim.queue_scroll(nvim::ScrollInput {
+        row: (y / line_height).trunc() as i64,
+        col: (x / char_width).trunc() as i64,
+        lines,
+        columns,
+    });
 }
 
 fn gtk_button_press(
@@ -1402,32 +1793,40 @@ fn gtk_button_press(
     modifier_state: ModifierType,
     menu: &gtk::PopoverMenu,
 ) {
-    if shell.mouse_enabled {
-        if button != 3 {
-            ui_state.borrow_mut().mouse_pressed = true;
-        }
-
-        match button {
-            1 => mouse_input(shell, "left", "press", modifier_state, (x, y)),
-            2 => mouse_input(shell, "middle", "press", modifier_state, (x, y)),
-            3 => {
-                menu.set_pointing_to(Some(&gdk::Rectangle::new(
-                    x.round() as i32,
-                    y.round() as i32,
-                    0,
-                    0,
-                )));
-
-                // Popping up the menu will trigger a focus event, so handle this in the idle loop
-                // to avoid a double borrow_mut()
-                glib::idle_add_local_once(glib::clone!(
-                    #[strong]
-                    menu,
-                    move || menu.popup()
-                ));
-            }
-            _ => (),
-        }
+    if button == 1
+        && modifier_state.contains(ModifierType::CONTROL_MASK)
+        && let Some(link) = link_at(shell, (x, y))
+    {
+        open_link(shell, link);
+        return;
+    }
+
+    if button == 3 && shell.context_menu {
+        // The menu pops up from the main loop once Neovim sent it, which also avoids a double
+        // borrow_mut() from the focus event popping it up triggers
+        shell.show_context_menu(menu, (x, y));
+        return;
+    }
+
+    if shell.mouse_enabled
+        && let Some(button) = mouse_button_name(button)
+    {
+        let (grid, ..) = mouse_coordinates_to_nvim(shell, (x, y), None);
+        let mut ui_state = ui_state.borrow_mut();
+        ui_state.pressed_button = Some(button);
+        ui_state.mouse_grid = Some(grid);
+
+        mouse_input(shell, button, "press", modifier_state, (x, y), Some(grid));
+    }
+}
+
+/// The name Neovim uses for a GTK mouse button
+fn mouse_button_name(button: u32) -> Option<&'static str> {
+    match button {
+        1 => Some("left"),
+        2 => Some("middle"),
+        3 => Some("right"),
+        _ => None,
     }
 }
 
@@ -1437,35 +1836,60 @@ fn mouse_input(
     action: &'static str,
     state: ModifierType,
     position: (f64, f64),
+    grid: Option<u64>,
 ) {
     if let Some(nvim) = shell.nvim() {
-        let (col, row) = mouse_coordinates_to_nvim(shell, position);
-        let input_string = keyval_to_input_string("", state);
-
-        let nvim_clone = nvim.clone();
-        nvim.spawn(async move {
-            nvim_clone
-                .input_mouse(button, action, &input_string, 0, row as i64, col as i64)
-                .await
-                .ok_and_report()
-                .expect("Can't send mouse input event");
+        let (grid, col, row) = mouse_coordinates_to_nvim(shell, position, grid);
+        nvim.queue_mouse(MouseInput {
+            button,
+            action,
+            modifier: keyval_to_input_string("", state),
+            grid: grid as i64,
+            row: row as i64,
+            col: col as i64,
         });
     }
 }
 
 /**
- * Translate gtk mouse event coordinates to nvim (col, row).
+ * Translate gtk mouse event coordinates to nvim (grid, col, row). If no grid is given, the topmost
+ * grid under the pointer is used. Without ext_multigrid, the grid is always 0.
  */
-fn mouse_coordinates_to_nvim(shell: &State, position: (f64, f64)) -> (u64, u64) {
+fn mouse_coordinates_to_nvim(
+    shell: &State,
+    position: (f64, f64),
+    grid: Option<u64>,
+) -> (u64, u64, u64) {
     let &CellMetrics {
         line_height,
         char_width,
         ..
     } = shell.render_state.borrow().font_ctx.cell_metrics();
     let (x, y) = position;
-    let col = (x / char_width).trunc() as u64;
-    let row = (y / line_height).trunc() as u64;
-    (col, row)
+    let (row, col) = (y / line_height, x / char_width);
+
+    let multigrid = shell
+        .nvim
+        .api_info()
+        .is_some_and(|api_info| api_info.ext_multigrid);
+    if !multigrid {
+        return (0, col.trunc() as u64, row.trunc() as u64);
+    }
+
+    let grid_pos = match grid {
+        Some(grid) => shell.grids.get(grid).map(|_| {
+            let (grid_row, grid_col) = shell.grids.origin(grid);
+            (grid, (row - grid_row, col - grid_col))
+        }),
+        None => shell.grids.grid_at((row, col)),
+    };
+    let (grid, (row, col)) = grid_pos.unwrap_or((DEFAULT_GRID, (row, col)));
+
+    (
+        grid,
+        col.max(0.0).trunc() as u64,
+        row.max(0.0).trunc() as u64,
+    )
 }
 
 fn gtk_button_release(
@@ -1476,17 +1900,18 @@ fn gtk_button_release(
     y: f64,
     modifier_state: ModifierType,
 ) {
-    if button != 3 {
-        ui_state.mouse_pressed = false;
+    let Some(button) = mouse_button_name(button) else {
+        return;
+    };
+    // Releases without a press, e.g. after our own context menu was shown, aren't forwarded
+    if ui_state.pressed_button != Some(button) {
+        return;
     }
+    ui_state.pressed_button = None;
+    let grid = ui_state.mouse_grid.take();
 
     if shell.mouse_enabled && !shell.nvim.is_initializing() {
-        match button {
-            1 => mouse_input(shell, "left", "release", modifier_state, (x, y)),
-            2 => mouse_input(shell, "middle", "release", modifier_state, (x, y)),
-            // We don't handle 3 here since that's used for the right click context menu
-            _ => (),
-        }
+        mouse_input(shell, button, "release", modifier_state, (x, y), grid);
     }
 }
 
@@ -1496,19 +1921,92 @@ fn gtk_motion_notify(
     position: (f64, f64),
     modifier_state: ModifierType,
 ) {
-    if shell.mouse_enabled && ui_state.mouse_pressed {
-        let pos = mouse_coordinates_to_nvim(shell, position);
+    if shell.mouse_enabled && !shell.nvim.is_initializing() {
+        let pos = mouse_coordinates_to_nvim(shell, position, ui_state.mouse_grid);
 
         // if we fire LeftDrag on the same coordinates multiple times, then
         // we get: https://github.com/daa84/neovim-gtk/issues/185
+        // Hover plugins don't care about moves within a cell either.
         if pos != ui_state.last_nvim_pos {
-            mouse_input(shell, "left", "drag", modifier_state, position);
+            match ui_state.pressed_button {
+                Some(button) => mouse_input(
+                    shell,
+                    button,
+                    "drag",
+                    modifier_state,
+                    position,
+                    ui_state.mouse_grid,
+                ),
+                None if shell.mouse_move_event => {
+                    mouse_input(shell, "move", "", modifier_state, position, None)
+                }
+                None => (),
+            }
             ui_state.last_nvim_pos = pos;
         }
     }
 
     ui_state.last_pos = position;
-    ui_state.set_cursor_visible(&shell.nvim_viewport, true);
+    let hover = hover_at(shell, position);
+    shell.update_pointer(|pointer| {
+        pointer.hidden = false;
+        pointer.hover = hover;
+    });
+}
+
+/// Find the topmost grid under the given position in the viewport, along with the cell in it
+fn grid_cell_at(shell: &State, position: (f64, f64)) -> Option<(&Grid, usize, usize)> {
+    let &CellMetrics {
+        line_height,
+        char_width,
+        ..
+    } = shell.render_state.borrow().font_ctx.cell_metrics();
+    let (x, y) = position;
+
+    let (grid, (row, col)) = shell.grids.grid_at((y / line_height, x / char_width))?;
+    Some((shell.grids.get(grid)?, row as usize, col as usize))
+}
+
+/// Find the link under the given position in the viewport, if there is one
+fn link_at(shell: &State, position: (f64, f64)) -> Option<Link> {
+    let (grid, row, col) = grid_cell_at(shell, position)?;
+    grid.model()
+        .link_at(row, col, &shell.render_state.borrow().hl)
+}
+
+fn hover_at(shell: &State, position: (f64, f64)) -> Hover {
+    if link_at(shell, position).is_some() {
+        return Hover::Link;
+    }
+
+    let hl = &shell.render_state.borrow().hl;
+    grid_cell_at(shell, position)
+        .and_then(|(grid, row, col)| {
+            let cell = grid.model().model().get(row)?.line.get(col)?;
+            hl.cell_hl(cell).separator
+        })
+        .map_or(Hover::Text, Hover::Separator)
+}
+
+fn open_link(shell: &State, link: Link) {
+    match link {
+        Link::Url(url) => {
+            let launch_ctx = shell.nvim_viewport.display().app_launch_context();
+            if let Err(err) = gio::AppInfo::launch_default_for_uri(&url, Some(&launch_ctx)) {
+                error!("Failed to open {url}: {err}");
+            }
+        }
+        Link::FileLine { path, line, column } => {
+            if let Some(nvim) = shell.nvim() {
+                let command = format!(
+                    "edit {} | call cursor({line}, {})",
+                    escape_filename(&path),
+                    column.unwrap_or(1)
+                );
+                spawn_timeout_user_err!(nvim.command(&command));
+            }
+        }
+    }
 }
 
 fn show_nvim_start_error(
@@ -1705,6 +2203,9 @@ fn set_nvim_initialized(state_arc: Arc<UiMutex<State>>, api_info: NeovimApiInfo)
         state_arc,
         move || {
             let mut state = state_arc.borrow_mut();
+            if let Some(action_widgets) = state.action_widgets.borrow().as_ref() {
+                action_widgets.set_messages_visible(api_info.ext_messages);
+            }
             state.nvim.set_initialized(api_info);
             // in some case resize can happens while initialization in progress
             // so force resize here
@@ -1753,17 +2254,38 @@ fn init_nvim(
 
 // Neovim redraw events
 impl State {
+    /// Queue a redraw for the next frame. Redraws get accumulated until the frame clock asks for
+    /// the next frame, so a burst of flushes from Neovim only results in a single snapshot.
     pub fn queue_draw(&mut self, mode: RedrawMode) {
         if mode == RedrawMode::Nothing {
             return;
         }
 
+        self.frame_redraw = self.frame_redraw.max(mode);
+        self.nvim_viewport.request_frame();
+    }
+
+    /// Queue popup menu changes for the next frame, so it moves along with the grids it's drawn on
+    pub fn queue_popupmenu(&mut self, pending: PendingPopupMenu) {
+        if pending == PendingPopupMenu::None {
+            return;
+        }
+
+        self.frame_popupmenu.update(pending);
+        self.nvim_viewport.request_frame();
+    }
+
+    /// Apply the redraws queued since the last frame, returning the popup menu changes to flush
+    /// once the state isn't borrowed mutably anymore
+    pub fn prepare_frame(&mut self) -> PendingPopupMenu {
+        let mode = mem::replace(&mut self.frame_redraw, RedrawMode::Nothing);
+        let popupmenu = self.frame_popupmenu.take();
+        if mode == RedrawMode::Nothing {
+            return popupmenu;
+        }
+
         if mode == RedrawMode::All {
-            if let Some(model) = self.grids.current_model() {
-                self.nvim_viewport.invalidate_snapshot_lines(model);
-            } else {
-                self.nvim_viewport.clear_snapshot_cache();
-            }
+            self.nvim_viewport.invalidate_snapshot_lines(&self.grids);
             self.update_dirty_glyphs();
         } else if mode >= RedrawMode::ClearCache {
             self.nvim_viewport.clear_snapshot_cache();
@@ -1771,6 +2293,7 @@ impl State {
 
         self.grids.flush_cursor();
         self.nvim_viewport.queue_draw();
+        popupmenu
     }
 
     pub fn grid_line(
@@ -1778,16 +2301,14 @@ impl State {
         grid: u64,
         row: u64,
         col_start: u64,
-        cells: Vec<Vec<Value>>,
+        cells: Vec<GridLineCell>,
     ) -> RedrawMode {
-        let hl = &self.render_state.borrow().hl;
-        self.grids[grid].line(row as usize, col_start as usize, cells, hl);
+        self.grids[grid].line(row as usize, col_start as usize, cells);
         RedrawMode::All
     }
 
     pub fn grid_clear(&mut self, grid: u64) -> RedrawMode {
-        let hl = &self.render_state.borrow().hl;
-        self.grids[grid].clear(&hl.default_hl());
+        self.grids[grid].clear();
         RedrawMode::All
     }
 
@@ -1798,6 +2319,7 @@ impl State {
 
     pub fn grid_cursor_goto(&mut self, grid: u64, row: u64, column: u64) -> RedrawMode {
         self.grids[grid].cursor_goto(row as usize, column as usize);
+        self.grids.set_current(grid);
         self.set_im_location();
         RedrawMode::Cursor
     }
@@ -1830,16 +2352,154 @@ impl State {
         rows: i64,
         cols: i64,
     ) -> RedrawMode {
-        let hl = &self.render_state.borrow().hl.default_hl();
-        self.grids[grid].scroll(top, bot, left, right, rows, cols, hl);
+        self.grids[grid].scroll(top, bot, left, right, rows, cols);
+
+        // Only whole lines can be moved around, so scrolls of part of a line (e.g. a window in a
+        // vertical split without ext_multigrid) just jump and get drawn again
+        if left == 0 && right as usize == self.grids[grid].model().columns {
+            if self.smooth_scroll {
+                self.nvim_viewport
+                    .animate_scroll(grid, top as usize, bot as usize, rows);
+            }
+            self.nvim_viewport
+                .scroll_snapshot_lines(grid, top as usize, bot as usize, rows);
+        }
+        RedrawMode::All
+    }
+
+    pub fn win_pos(
+        &mut self,
+        grid: u64,
+        _win: Value,
+        start_row: u64,
+        start_col: u64,
+        width: u64,
+        height: u64,
+    ) -> RedrawMode {
+        debug!("win_pos {grid} at {start_row}/{start_col}, {width}x{height}");
+
+        self.grids.set_window_position(grid, start_row, start_col);
         RedrawMode::All
     }
 
+    pub fn win_float_pos(
+        &mut self,
+        grid: u64,
+        _win: Value,
+        anchor: String,
+        anchor_grid: u64,
+        anchor_row: f64,
+        anchor_col: f64,
+        _focusable: bool,
+        zindex: u64,
+    ) -> RedrawMode {
+        let anchor = match FloatAnchor::new(&anchor) {
+            Ok(anchor) => anchor,
+            Err(e) => {
+                error!("{e}");
+                return RedrawMode::Nothing;
+            }
+        };
+
+        self.grids
+            .set_float_position(grid, anchor, anchor_grid, anchor_row, anchor_col, zindex);
+        RedrawMode::All
+    }
+
+    pub fn win_hide(&mut self, grid: u64) -> RedrawMode {
+        self.grids.hide(grid);
+        RedrawMode::All
+    }
+
+    pub fn win_close(&mut self, grid: u64) -> RedrawMode {
+        self.grids.close(grid);
+        RedrawMode::All
+    }
+
+    pub fn msg_set_pos(&mut self, grid: u64, row: u64, zindex: Option<u64>) -> RedrawMode {
+        self.grids.set_msg_position(grid, row, zindex);
+        RedrawMode::All
+    }
+
+    pub fn win_viewport(&mut self, grid: u64, viewport: GridViewport) -> RedrawMode {
+        self.grids.get_or_create(grid).set_viewport(viewport);
+        RedrawMode::Nothing
+    }
+
+    pub fn msg_show(&mut self, message: Message, replace_last: bool) -> RedrawMode {
+        // Prompts block Neovim until they're answered, so show them as dialogs instead of toasts
+        if let Some(prompt) = Prompt::new(&message.kind, &message.text())
+            && let Some(nvim) = self.nvim()
+        {
+            if let Some(dlg) = self.prompt_dlg.take() {
+                dlg.dismiss();
+            }
+
+            let window = self.nvim_viewport.root().and_downcast::<gtk::Window>();
+            self.prompt_dlg = Some(shell_dlg::show_prompt_dlg(window.as_ref(), prompt, nvim));
+            return RedrawMode::Nothing;
+        }
+
+        self.messages
+            .show(&message, replace_last, &self.render_state.borrow());
+        RedrawMode::Nothing
+    }
+
+    /// Switch between showing messages ourselves and leaving them to Neovim's message grid
+    pub fn set_messages_external(&mut self, external: bool) {
+        if let Some(action_widgets) = self.action_widgets.borrow().as_ref() {
+            action_widgets.set_messages_visible(external);
+        }
+        if !external {
+            self.msg_clear();
+        }
+    }
+
+    pub fn msg_clear(&mut self) -> RedrawMode {
+        if let Some(dlg) = self.prompt_dlg.take() {
+            dlg.dismiss();
+        }
+        self.messages.clear();
+        RedrawMode::Nothing
+    }
+
+    pub fn msg_showmode(&mut self, content: Message) -> RedrawMode {
+        self.status_strip
+            .set_mode(&content, &self.render_state.borrow());
+        RedrawMode::Nothing
+    }
+
+    pub fn msg_showcmd(&mut self, content: Message) -> RedrawMode {
+        self.status_strip
+            .set_showcmd(&content, &self.render_state.borrow());
+        RedrawMode::Nothing
+    }
+
+    pub fn msg_ruler(&mut self, content: Message) -> RedrawMode {
+        self.status_strip
+            .set_ruler(&content, &self.render_state.borrow());
+        RedrawMode::Nothing
+    }
+
+    pub fn msg_history_show(&mut self, entries: Vec<Message>) -> RedrawMode {
+        self.messages
+            .show_history(&entries, &self.render_state.borrow());
+        RedrawMode::Nothing
+    }
+
+    pub fn msg_history_clear(&mut self) -> RedrawMode {
+        self.messages.clear_history();
+        RedrawMode::Nothing
+    }
+
+    pub fn message_history_popover(&self) -> gtk::Popover {
+        self.messages.history_popover().clone()
+    }
+
     pub fn hl_attr_define(
         &mut self,
         id: u64,
         rgb_attr: HashMap<String, Value>,
-        _: &Value,
         info: Vec<HashMap<String, Value>>,
     ) -> RedrawMode {
         let mut render_state = self.render_state.borrow_mut();
@@ -1880,9 +2540,17 @@ impl State {
                 COLOR_BLACK
             },
         );
+        self.update_messages_css();
         RedrawMode::ClearCache
     }
 
+    /// The message toasts and the status strip use the default colors and font of the viewport
+    fn update_messages_css(&self) {
+        let render_state = self.render_state.borrow();
+        self.messages.update_css(&render_state);
+        self.status_strip.update_css(&render_state);
+    }
+
     fn cur_point_area(&self) -> RedrawMode {
         if self.grids.current().is_some() {
             RedrawMode::Cursor
@@ -1900,12 +2568,19 @@ impl State {
             .set_mode_info(render_state.mode.mode_info().cloned());
         self.cmd_line
             .set_mode_info(render_state.mode.mode_info().cloned());
+        let mode_shape = render_state
+            .mode
+            .mode_info()
+            .and_then(|mode_info| mode_info.mouse_shape)
+            .and_then(mouse_shape_name);
+        self.update_pointer(|pointer| pointer.mode_shape = mode_shape);
 
         self.cur_point_area()
     }
 
     pub fn on_mouse(&mut self, on: bool) -> RedrawMode {
         self.mouse_enabled = on;
+        self.update_pointer(|pointer| pointer.mouse_enabled = on);
         RedrawMode::Nothing
     }
 
@@ -1915,10 +2590,38 @@ impl State {
         } else {
             self.cursor.as_mut().unwrap().busy_off();
         }
+        self.update_pointer(|pointer| pointer.busy = busy);
 
         self.cur_point_area()
     }
 
+    pub fn on_bell(&mut self, visual: bool) -> RedrawMode {
+        if visual {
+            self.nvim_viewport.flash();
+        } else {
+            self.nvim_viewport.display().beep();
+        }
+
+        // GTK 4 dropped urgency hints, so a notification stands in for one when the window isn't
+        // focused. It gets withdrawn again once the window is.
+        if let Some(window) = self.nvim_viewport.root().and_downcast::<gtk::Window>()
+            && !window.is_active()
+            && let Some(app) = window.application()
+        {
+            let notification =
+                gio::Notification::new(window.title().as_deref().unwrap_or("Neovim"));
+            notification.set_body(Some("Neovim rang the bell"));
+            app.send_notification(Some(BELL_NOTIFICATION), &notification);
+        }
+
+        RedrawMode::Nothing
+    }
+
+    pub fn set_title(&mut self, title: String) -> RedrawMode {
+        self.on_command(nvim::NvimCommand::SetTitle(title));
+        RedrawMode::Nothing
+    }
+
     pub fn set_pending_popupmenu(&mut self, new_pending: PendingPopupMenu) -> RedrawMode {
         self.pending_popupmenu.update(new_pending);
         RedrawMode::Nothing
@@ -1946,6 +2649,24 @@ impl State {
     pub fn option_set(&mut self, name: String, val: Value) -> RedrawMode {
         match name.as_str() {
             "guifont" => self.set_font_from_value(val),
+            "mousemoveevent" => {
+                self.mouse_move_event = val.as_bool().unwrap_or(false);
+                RedrawMode::Nothing
+            }
+            "pumblend" => {
+                let render_state = &mut *self.render_state.borrow_mut();
+                render_state.hl.set_pumblend(val.as_u64().unwrap_or(0));
+                self.popup_menu
+                    .update_css(&render_state.hl, &render_state.font_ctx);
+                RedrawMode::Nothing
+            }
+            "mousehide" => {
+                self.mouse_hide = val.as_bool().unwrap_or(true);
+                if !self.mouse_hide {
+                    self.update_pointer(|pointer| pointer.hidden = false);
+                }
+                RedrawMode::Nothing
+            }
             _ => RedrawMode::Nothing,
         }
     }
@@ -2009,9 +2730,7 @@ impl State {
         level: u64,
     ) -> RedrawMode {
         {
-            let cursor = self.grids.current().unwrap().cur_point();
-            let render_state = self.render_state.borrow();
-            let (x, y, width, height) = cursor.to_area(render_state.font_ctx.cell_metrics());
+            let (x, y, width, height) = self.cursor_area().unwrap();
             let ctx = CmdLineContext {
                 nvim: &self.nvim,
                 content,
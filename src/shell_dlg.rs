@@ -1,4 +1,9 @@
-use std::{cell::RefCell, convert::*, rc::Rc, sync::Arc};
+use std::{
+    cell::{Cell, RefCell},
+    convert::*,
+    rc::Rc,
+    sync::Arc,
+};
 
 use log::{error, warn};
 
@@ -139,3 +144,344 @@ fn get_changed_buffers(nvim: &NvimSession) -> Result<Vec<String>, SessionError>
         .map(|e| e.1)
         .collect())
 }
+
+/// A prompt from Neovim that blocks until the user answers it, shown as a native dialog when
+/// ext_messages is enabled
+#[derive(Debug, PartialEq, Eq)]
+pub struct Prompt {
+    pub message: String,
+    pub choices: Vec<PromptChoice>,
+    pub default: Option<usize>,
+    /// The input to send if the dialog is dismissed without picking a choice
+    pub cancel_input: &'static str,
+    /// Whether the choices are a list to pick from, rather than a few buttons
+    pub list: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PromptChoice {
+    pub label: String,
+    /// The input to send to Neovim when this choice is picked
+    pub input: String,
+}
+
+impl PromptChoice {
+    fn new(label: &str, input: &str) -> Self {
+        PromptChoice {
+            label: label.to_owned(),
+            input: input.to_owned(),
+        }
+    }
+
+    /// The label with a mnemonic for the first occurrence of the choice's key
+    fn mnemonic_label(&self) -> String {
+        let label = self.label.replace('_', "__");
+        let key = self.input.to_lowercase();
+        match label.to_lowercase().find(&key) {
+            Some(idx) if key.chars().count() == 1 && label.is_char_boundary(idx) => {
+                format!("{}_{}", &label[..idx], &label[idx..])
+            }
+            _ => label,
+        }
+    }
+}
+
+impl Prompt {
+    /// Create a prompt from the text of a msg_show event, if its kind is one we handle
+    pub fn new(kind: &str, text: &str) -> Option<Self> {
+        match kind {
+            "confirm" => Some(Self::confirm(text)),
+            "confirm_sub" => Some(Self::confirm_sub(text)),
+            "return_prompt" => Some(Prompt {
+                message: text.trim().to_owned(),
+                choices: vec![PromptChoice::new("Continue", "<CR>")],
+                default: Some(0),
+                cancel_input: "<CR>",
+                list: false,
+            }),
+            "number_prompt" => Self::number_prompt(text),
+            _ => None,
+        }
+    }
+
+    /// Parse a prompt from inputlist() or z=, which lists numbered items followed by a line like
+    /// "Type number and <Enter> or click with the mouse (q or empty cancels): "
+    fn number_prompt(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let items = text.rsplit_once('\n').map_or("", |(items, _)| items);
+
+        let mut message = Vec::new();
+        let mut choices = Vec::new();
+        for line in items.lines().filter(|line| !line.trim().is_empty()) {
+            let number: String = line
+                .trim_start()
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect();
+            if number.is_empty() {
+                message.push(line.trim());
+            } else {
+                choices.push(PromptChoice::new(line.trim(), &format!("{number}<CR>")));
+            }
+        }
+
+        // Without any items there's nothing to pick from, so leave it to the user to type in
+        if choices.is_empty() {
+            return None;
+        }
+
+        Some(Prompt {
+            message: message.join("\n"),
+            choices,
+            default: None,
+            cancel_input: "<Esc>",
+            list: true,
+        })
+    }
+
+    /// Parse a prompt from confirm(), where the last line lists the choices like
+    /// "(Y)es, [N]o, (C)ancel: " and the default choice is in square brackets
+    fn confirm(text: &str) -> Self {
+        let text = text.trim();
+        let (message, choices_line) = text.rsplit_once('\n').unwrap_or(("", text));
+
+        let mut choices = Vec::new();
+        let mut default = None;
+        for choice in choices_line.trim_end_matches([':', ' ']).split(", ") {
+            let Some(start) = choice.find(['(', '[']) else {
+                continue;
+            };
+            let mut key_chars = choice[start + 1..].chars();
+            let (Some(key), Some(close)) = (key_chars.next(), key_chars.next()) else {
+                continue;
+            };
+            if !matches!(close, ')' | ']') {
+                continue;
+            }
+
+            if choice[start..].starts_with('[') {
+                default = Some(choices.len());
+            }
+            let label = format!(
+                "{}{key}{}",
+                &choice[..start],
+                &choice[start + 2 + key.len_utf8()..]
+            );
+            choices.push(PromptChoice::new(&label, &key.to_string()));
+        }
+
+        Prompt {
+            message: message.trim().to_owned(),
+            choices,
+            default,
+            cancel_input: "<Esc>",
+            list: false,
+        }
+    }
+
+    /// Parse a prompt from :s///c, which looks like "replace with foo (y/n/a/q/l/^E/^Y)?"
+    fn confirm_sub(text: &str) -> Self {
+        let text = text.trim();
+        let (message, keys) = text
+            .rsplit_once(" (")
+            .map(|(message, keys)| (message, keys.trim_end_matches(['?', ')'])))
+            .unwrap_or((text, ""));
+
+        let choices = keys
+            .split('/')
+            .filter_map(|key| {
+                let label = match key {
+                    "y" => "Yes",
+                    "n" => "No",
+                    "a" => "All",
+                    "q" => "Quit",
+                    "l" => "Last",
+                    _ => return None,
+                };
+                Some(PromptChoice::new(label, key))
+            })
+            .collect();
+
+        Prompt {
+            message: message.to_owned(),
+            choices,
+            default: Some(0),
+            cancel_input: "<Esc>",
+            list: false,
+        }
+    }
+}
+
+/// A prompt dialog waiting for an answer
+pub struct PromptDlg {
+    dlg: MessageDialog,
+    answered: Rc<Cell<bool>>,
+}
+
+impl PromptDlg {
+    /// Close the dialog without sending anything to Neovim, for when the prompt went away on its
+    /// own
+    pub fn dismiss(self) {
+        self.answered.set(true);
+        self.dlg.destroy();
+    }
+}
+
+/// Show a prompt from Neovim as a modal dialog, and send the input for the chosen answer back to
+/// Neovim
+pub fn show_prompt_dlg(
+    parent: Option<&gtk::Window>,
+    prompt: Prompt,
+    nvim: NvimSession,
+) -> PromptDlg {
+    let flags = gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dlg = MessageDialog::new(
+        parent,
+        flags,
+        MessageType::Question,
+        ButtonsType::None,
+        &prompt.message,
+    );
+
+    if prompt.list {
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .activate_on_single_click(true)
+            .build();
+        for choice in &prompt.choices {
+            list.append(
+                &gtk::Label::builder()
+                    .label(&choice.label)
+                    .xalign(0.0)
+                    .build(),
+            );
+        }
+        list.connect_row_activated(glib::clone!(
+            #[weak]
+            dlg,
+            move |_, row| dlg.response(gtk::ResponseType::Other(row.index() as u16))
+        ));
+
+        let scroll = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(400)
+            .child(&list)
+            .build();
+        dlg.message_area()
+            .downcast::<gtk::Box>()
+            .expect("message area is a box")
+            .append(&scroll);
+        dlg.add_button("_Cancel", gtk::ResponseType::Cancel);
+    } else {
+        for (idx, choice) in prompt.choices.iter().enumerate() {
+            dlg.add_button(
+                &choice.mnemonic_label(),
+                gtk::ResponseType::Other(idx as u16),
+            );
+        }
+    }
+    if let Some(default) = prompt.default {
+        dlg.set_default_response(gtk::ResponseType::Other(default as u16));
+    }
+
+    let answered = Rc::new(Cell::new(false));
+    dlg.connect_response(glib::clone!(
+        #[strong]
+        answered,
+        move |dlg, response| {
+            // Only answer once, closing the dialog reports a response of its own
+            if answered.replace(true) {
+                return;
+            }
+
+            let input = match response {
+                gtk::ResponseType::Other(idx) => prompt
+                    .choices
+                    .get(idx as usize)
+                    .map_or(prompt.cancel_input, |choice| choice.input.as_str()),
+                _ => prompt.cancel_input,
+            }
+            .to_owned();
+
+            dlg.destroy();
+            nvim.queue_input(input);
+        }
+    ));
+
+    dlg.show();
+    PromptDlg { dlg, answered }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm_prompt() {
+        let prompt = Prompt::new(
+            "confirm",
+            "\nSave changes to \"foo.txt\"?\n[Y]es, (N)o, (C)ancel: ",
+        )
+        .unwrap();
+
+        assert_eq!("Save changes to \"foo.txt\"?", prompt.message);
+        assert_eq!(
+            vec![
+                PromptChoice::new("Yes", "Y"),
+                PromptChoice::new("No", "N"),
+                PromptChoice::new("Cancel", "C"),
+            ],
+            prompt.choices
+        );
+        assert_eq!(Some(0), prompt.default);
+        assert_eq!("_Yes", prompt.choices[0].mnemonic_label());
+    }
+
+    #[test]
+    fn test_confirm_sub_prompt() {
+        let prompt = Prompt::new("confirm_sub", "replace with bar (y/n/a/q/l/^E/^Y)?").unwrap();
+
+        assert_eq!("replace with bar", prompt.message);
+        assert_eq!(
+            vec!["y", "n", "a", "q", "l"],
+            prompt
+                .choices
+                .iter()
+                .map(|choice| choice.input.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_number_prompt() {
+        let prompt = Prompt::new(
+            "number_prompt",
+            "Pick one:\n1. foo\n2. bar\nType number and <Enter> or click with the mouse (q or empty cancels): ",
+        )
+        .unwrap();
+
+        assert_eq!("Pick one:", prompt.message);
+        assert_eq!(
+            vec![
+                PromptChoice::new("1. foo", "1<CR>"),
+                PromptChoice::new("2. bar", "2<CR>"),
+            ],
+            prompt.choices
+        );
+        assert!(prompt.list);
+
+        assert_eq!(
+            None,
+            Prompt::new(
+                "number_prompt",
+                "Type number and <Enter> (q or empty cancels): "
+            )
+        );
+    }
+
+    #[test]
+    fn test_other_kinds_are_not_prompts() {
+        assert_eq!(None, Prompt::new("echomsg", "Hello"));
+    }
+}
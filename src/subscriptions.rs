@@ -49,7 +49,7 @@ pub struct Subscriptions(HashMap<SubscriptionKey, Vec<Subscription>>);
 /// Can be used to trigger the subscription manually even when the event was not triggered.
 ///
 /// Could be used in the future to suspend individual subscriptions.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SubscriptionHandle {
     key: SubscriptionKey,
     index: usize,
@@ -16,6 +16,7 @@ use serde::{Deserialize, Serialize};
 use crate::Args;
 use crate::file_browser::FileBrowserWidget;
 use crate::highlight::BackgroundState;
+use crate::menubar::MenuBars;
 use crate::misc::{self, BoolExt};
 use crate::nvim::*;
 use crate::plug_manager;
@@ -44,6 +45,10 @@ pub struct Components {
     window: Option<ApplicationWindow>,
     window_state: ToplevelState,
     title_label: Option<gtk::Label>,
+    /// The short and long title generated from the current file
+    file_title: (String, String),
+    /// The title from Neovim, when 'title' is set
+    nvim_title: Option<String>,
     pub exit_confirmed: bool,
 }
 
@@ -53,6 +58,8 @@ impl Components {
             window: None,
             window_state: ToplevelState::load(),
             title_label: None,
+            file_title: (String::new(), String::new()),
+            nvim_title: None,
             exit_confirmed: false,
         }
     }
@@ -65,7 +72,24 @@ impl Components {
         self.window.as_ref().unwrap()
     }
 
-    pub fn set_title(&self, short_title: &str, long_title: &str) {
+    pub fn set_title(&mut self, short_title: &str, long_title: &str) {
+        self.file_title = (short_title.to_owned(), long_title.to_owned());
+        self.update_title();
+    }
+
+    /// Use the title Neovim sends instead of our own. Neovim sends an empty title once 'title' is
+    /// turned off.
+    pub fn set_nvim_title(&mut self, title: String) {
+        self.nvim_title = (!title.is_empty()).then_some(title);
+        self.update_title();
+    }
+
+    fn update_title(&self) {
+        let (short_title, long_title) = match self.nvim_title {
+            Some(ref title) => (title, title),
+            None => (&self.file_title.0, &self.file_title.1),
+        };
+
         self.window.as_ref().unwrap().set_title(Some(long_title));
         if let Some(ref title_label) = self.title_label {
             title_label.set_label(short_title);
@@ -270,7 +294,13 @@ impl Ui {
         let file_browser = self.file_browser.borrow();
         main.set_start_child(Some(&**file_browser));
         main.set_end_child(Some(&**shell));
-        window.set_child(Some(&main));
+
+        let menu_bars = Rc::new(MenuBars::new(&shell.state));
+        let content = gtk::Box::new(Orientation::Vertical, 0);
+        main.set_vexpand(true);
+        content.append(&**menu_bars);
+        content.append(&main);
+        window.set_child(Some(&content));
 
         window.show();
 
@@ -336,6 +366,17 @@ impl Ui {
             autocmds.push(autocmd);
         }
 
+        let update_menus = state.subscribe(
+            SubscriptionKey::from("MenuPopup"),
+            &["json_encode(menu_get(''))", "mode()"],
+            glib::clone!(
+                #[weak]
+                menu_bars,
+                move |args| menu_bars.update_from_json(&args[0], &args[1])
+            ),
+        );
+        autocmds.push(update_menus.clone());
+
         window.connect_close_request(glib::clone!(
             #[weak]
             shell_ref,
@@ -390,11 +431,20 @@ impl Ui {
         )));
 
         let sidebar_action = UiMutex::new(show_sidebar_action);
+        let menu_bars = UiMutex::new(menu_bars);
         let comps_ref = comps_ref.clone();
         let projects = self.projects.clone();
         shell.set_nvim_command_cb(Some(
             move |shell: &mut shell::State, command: NvimCommand| {
-                Ui::nvim_command(shell, command, &sidebar_action, &projects, &comps_ref);
+                Ui::nvim_command(
+                    shell,
+                    command,
+                    &sidebar_action,
+                    &menu_bars,
+                    &update_menus,
+                    &projects,
+                    &comps_ref,
+                );
             },
         ));
     }
@@ -494,6 +544,8 @@ impl Ui {
         shell: &mut shell::State,
         command: NvimCommand,
         sidebar_action: &UiMutex<SimpleAction>,
+        menu_bars: &UiMutex<Rc<MenuBars>>,
+        update_menus: &SubscriptionHandle,
         projects: &Arc<UiMutex<Projects>>,
         comps: &UiMutex<Components>,
     ) {
@@ -518,6 +570,10 @@ impl Ui {
                 let state = !bool::from_variant(&action.state().unwrap()).unwrap();
                 action.change_state(&state.to_variant());
             }
+            NvimCommand::Menubar(enabled) => menu_bars.borrow().set_menubar_enabled(enabled),
+            NvimCommand::Toolbar(enabled) => menu_bars.borrow().set_toolbar_enabled(enabled),
+            NvimCommand::RefreshMenus => shell.run_now(update_menus),
+            NvimCommand::SetTitle(title) => comps.borrow_mut().set_nvim_title(title),
             NvimCommand::Transparency(background_alpha, filled_alpha) => {
                 let comps = comps.borrow();
                 let window = comps.window.as_ref().unwrap();
@@ -589,6 +645,9 @@ impl Ui {
         primary_menu_btn.set_sensitive(false);
         header_bar.pack_end(&primary_menu_btn);
 
+        let messages_btn = self.create_messages_btn();
+        header_bar.pack_end(&messages_btn);
+
         let paste_btn = Button::from_icon_name("edit-paste-symbolic");
         let shell = self.shell.clone();
         paste_btn.connect_clicked(move |_| shell.borrow().edit_paste());
@@ -625,10 +684,31 @@ impl Ui {
                 paste_btn,
                 save_btn,
                 primary_menu_btn,
+                messages_btn,
             )),
         )
     }
 
+    fn create_messages_btn(&self) -> gtk::MenuButton {
+        let state = self.shell.borrow().state.clone();
+        let state = state.borrow();
+        let popover = state.message_history_popover();
+        let btn = gtk::MenuButton::builder()
+            .focusable(false)
+            .icon_name("document-open-recent-symbolic")
+            .tooltip_text("Message history")
+            .popover(&popover)
+            .visible(false)
+            .build();
+
+        let drawing_area = state.nvim_viewport.clone();
+        popover.connect_closed(move |_| {
+            drawing_area.grab_focus();
+        });
+
+        btn
+    }
+
     fn create_primary_menu_btn(
         &self,
         app: &gtk::Application,
@@ -875,7 +955,7 @@ fn update_window_title(comps: &Arc<UiMutex<Components>>, args: Vec<String>) {
         return;
     }
 
-    comps.borrow().set_title(
+    comps.borrow_mut().set_title(
         &format_window_title(file_path, dir, argidx, argc, modified, modifiable, false),
         &format_window_title(file_path, dir, argidx, argc, modified, modifiable, true),
     );
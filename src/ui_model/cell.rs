@@ -1,11 +1,12 @@
-use std::rc::Rc;
-
-use crate::highlight::Highlight;
+use super::grapheme::Grapheme;
 
+/// A single cell of a grid. Cells hold no heap data besides the odd long grapheme, so clearing and
+/// scrolling lines is mostly a matter of copying memory around.
 #[derive(Clone)]
 pub struct Cell {
-    pub hl: Rc<Highlight>,
-    pub ch: String,
+    pub ch: Grapheme,
+    /// The id of the cell's highlight in the `HighlightMap`, 0 being the default highlight
+    pub hl_id: u64,
     pub dirty: bool,
     pub double_width: bool,
 }
@@ -13,17 +14,10 @@ pub struct Cell {
 impl Cell {
     pub fn new_empty() -> Cell {
         Cell {
-            hl: Rc::new(Highlight::new()),
-            ch: String::new(),
+            ch: Grapheme::EMPTY,
+            hl_id: 0,
             dirty: true,
             double_width: false,
         }
     }
-
-    pub fn clear(&mut self, hl: Rc<Highlight>) {
-        self.ch.clear();
-        self.hl = hl;
-        self.dirty = true;
-        self.double_width = false;
-    }
 }
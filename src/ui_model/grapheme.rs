@@ -0,0 +1,101 @@
+use std::{fmt, ops::Deref, rc::Rc};
+
+/// Longest grapheme stored inline, enough for any single code point and most combined characters
+const INLINE_LEN: usize = 6;
+
+/// The text of a cell, which is cheap to clone around. Graphemes too long to be stored inline
+/// (emoji sequences, stacks of combining characters) share one allocation between the cells they
+/// get copied to, which is freed along with the last of them.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Grapheme {
+    Inline { len: u8, bytes: [u8; INLINE_LEN] },
+    Shared(Rc<str>),
+}
+
+impl Grapheme {
+    pub const EMPTY: Grapheme = Grapheme::Inline {
+        len: 0,
+        bytes: [0; INLINE_LEN],
+    };
+
+    pub fn new(s: &str) -> Self {
+        if s.len() <= INLINE_LEN {
+            let mut bytes = [0; INLINE_LEN];
+            bytes[..s.len()].copy_from_slice(s.as_bytes());
+            Grapheme::Inline {
+                len: s.len() as u8,
+                bytes,
+            }
+        } else {
+            Grapheme::Shared(s.into())
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            // SAFETY: The bytes are always copied from a whole &str in Grapheme::new()
+            Grapheme::Inline { len, bytes } => unsafe {
+                std::str::from_utf8_unchecked(&bytes[..*len as usize])
+            },
+            Grapheme::Shared(s) => s,
+        }
+    }
+}
+
+impl Default for Grapheme {
+    fn default() -> Self {
+        Grapheme::EMPTY
+    }
+}
+
+impl From<&str> for Grapheme {
+    fn from(s: &str) -> Self {
+        Grapheme::new(s)
+    }
+}
+
+impl Deref for Grapheme {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<&str> for Grapheme {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<Grapheme> for &str {
+    fn eq(&self, other: &Grapheme) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl fmt::Debug for Grapheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grapheme() {
+        assert_eq!("", Grapheme::default());
+        assert_eq!("a", Grapheme::new("a"));
+        assert_eq!("e\u{301}", Grapheme::new("e\u{301}"));
+        assert!(matches!(Grapheme::new("e\u{301}"), Grapheme::Inline { .. }));
+
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let grapheme = Grapheme::new(family);
+        assert!(matches!(grapheme, Grapheme::Shared(_)));
+        assert_eq!(family, grapheme);
+        assert_eq!(grapheme, Grapheme::new(family));
+        assert_ne!(grapheme, Grapheme::new("👨\u{200d}👩"));
+    }
+}
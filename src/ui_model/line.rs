@@ -1,14 +1,13 @@
 use std::{
     iter::Peekable,
     ops::{Index, IndexMut},
-    rc::Rc,
     slice::Iter,
 };
 
 use super::cell::Cell;
 use super::item::Item;
 use crate::color;
-use crate::highlight::{Highlight, HighlightMap};
+use crate::highlight::HighlightMap;
 use crate::render;
 
 pub struct Line {
@@ -62,9 +61,8 @@ impl Line {
         self.dirty_line = true;
     }
 
-    pub fn swap_with(&mut self, target: &mut Self, left: usize, right: usize) {
-        // swap is faster then clone
-        target.line[left..=right].swap_with_slice(&mut self.line[left..=right]);
+    pub fn copy_to(&self, target: &mut Self, left: usize, right: usize) {
+        target.line[left..=right].clone_from_slice(&self.line[left..=right]);
 
         // this is because copy can change Item layout
         target.dirty_line = true;
@@ -73,10 +71,8 @@ impl Line {
         }
     }
 
-    pub fn clear(&mut self, left: usize, right: usize, default_hl: &Rc<Highlight>) {
-        for cell in &mut self.line[left..=right] {
-            cell.clear(default_hl.clone());
-        }
+    pub fn clear(&mut self, left: usize, right: usize) {
+        self.line[left..=right].fill(Cell::new_empty());
         self.dirty_line = true;
     }
 
@@ -284,7 +280,7 @@ mod resize_tests {
     #[test]
     fn test_resize_preserves_prefix_state_and_only_initializes_new_cells() {
         let mut line = Line::new(2);
-        line.line[0].ch.push('a');
+        line.line[0].ch = "a".into();
         line.line[0].dirty = false;
         line.line[1].dirty = false;
         line.cell_to_item[0] = 0;
@@ -450,8 +446,8 @@ impl<'c> StyleAttr<'c> {
 
     fn from(start_idx: usize, end_idx: usize, cell: &'c Cell, hl: &'c HighlightMap) -> Self {
         StyleAttr {
-            italic: cell.hl.italic,
-            bold: cell.hl.bold,
+            italic: hl.cell_hl(cell).italic,
+            bold: hl.cell_hl(cell).bold,
             foreground: hl.cell_fg(cell),
             background: hl.cell_bg(cell),
             empty: false,
@@ -540,9 +536,9 @@ mod tests {
     #[test]
     fn test_styled_line() {
         let mut line = Line::new(3);
-        line[0].ch = "a".to_owned();
-        line[1].ch = "b".to_owned();
-        line[2].ch = "c".to_owned();
+        line[0].ch = "a".into();
+        line[1].ch = "b".into();
+        line[2].ch = "c".into();
 
         let styled_line =
             StyledLine::from(&line, &HighlightMap::new(), &render::FontFeatures::new());
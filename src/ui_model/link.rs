@@ -0,0 +1,111 @@
+/// URL schemes we detect in plain text
+const URL_SCHEMES: &[&str] = &["http://", "https://", "ftp://", "file://", "mailto:"];
+
+/// Something in the grid that can be opened with Ctrl+click
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Link {
+    /// A URL, either from the url highlight attribute or detected in the text
+    Url(String),
+    /// A path followed by a line number and optionally a column, like in compiler output
+    FileLine {
+        path: String,
+        line: u64,
+        column: Option<u64>,
+    },
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '"' | '\'' | '`' | '<' | '>' | '(' | ')' | '[' | ']' | '{' | '}' | '|'
+        )
+}
+
+/// Find a URL or path:line pattern in `text` that covers the byte at `idx`
+pub fn find_link(text: &str, idx: usize) -> Option<Link> {
+    if !text.is_char_boundary(idx) || text[idx..].starts_with(is_delimiter) {
+        return None;
+    }
+
+    let start = text[..idx].rfind(is_delimiter).map_or(0, |pos| {
+        pos + text[pos..].chars().next().unwrap().len_utf8()
+    });
+    let end = text[idx..]
+        .find(is_delimiter)
+        .map_or(text.len(), |pos| idx + pos);
+
+    // Punctuation at the end of a sentence usually isn't part of the link
+    let token = text[start..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    if idx >= start + token.len() {
+        return None;
+    }
+
+    if URL_SCHEMES
+        .iter()
+        .any(|scheme| token.len() > scheme.len() && token.starts_with(scheme))
+    {
+        return Some(Link::Url(token.to_owned()));
+    }
+
+    let mut parts = token.split(':');
+    let path = parts.next()?;
+    let line = parts.next()?.parse().ok()?;
+    let column = match parts.next() {
+        Some(column) => Some(column.parse().ok()?),
+        None => None,
+    };
+    // Avoid matching things like times, a path should at least have a directory or extension
+    if parts.next().is_some() || !path.contains(['/', '.']) {
+        return None;
+    }
+
+    Some(Link::FileLine {
+        path: path.to_owned(),
+        line,
+        column,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_url() {
+        let text = "See https://neovim.io/doc. for more";
+        let url = Some(Link::Url("https://neovim.io/doc".to_owned()));
+
+        assert_eq!(url, find_link(text, 4));
+        assert_eq!(url, find_link(text, 12));
+        assert_eq!(None, find_link(text, 25));
+        assert_eq!(None, find_link(text, 3));
+        assert_eq!(None, find_link("see http:// here", 6));
+        assert_eq!(
+            Some(Link::Url("https://example.com".to_owned())),
+            find_link("(https://example.com)", 5)
+        );
+    }
+
+    #[test]
+    fn test_find_file_line() {
+        assert_eq!(
+            Some(Link::FileLine {
+                path: "src/main.rs".to_owned(),
+                line: 12,
+                column: Some(5),
+            }),
+            find_link("error at src/main.rs:12:5: oops", 12)
+        );
+        assert_eq!(
+            Some(Link::FileLine {
+                path: "foo.c".to_owned(),
+                line: 3,
+                column: None,
+            }),
+            find_link("foo.c:3", 0)
+        );
+        assert_eq!(None, find_link("at 12:30 today", 4));
+        assert_eq!(None, find_link("src/main.rs", 2));
+    }
+}
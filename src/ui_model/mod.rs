@@ -1,15 +1,18 @@
-use crate::highlight::Highlight;
-use std::rc::Rc;
+use crate::highlight::HighlightMap;
 
 mod cell;
+mod grapheme;
 mod item;
 mod line;
+mod link;
 mod model_layout;
 mod model_rect;
 
 pub use self::cell::Cell;
+pub use self::grapheme::Grapheme;
 pub use self::item::Item;
 pub use self::line::{Line, StyledLine};
+pub use self::link::Link;
 pub use self::model_layout::{HighlightedLine, HighlightedRange, ModelLayout};
 pub use self::model_rect::ModelRect;
 
@@ -71,6 +74,31 @@ impl UiModel {
         &mut self.model
     }
 
+    /// Find a link at the given cell, either from the url attribute of its highlight or by
+    /// detecting URLs and path:line patterns in the text of the row
+    pub fn link_at(&self, row: usize, col: usize, hl: &HighlightMap) -> Option<Link> {
+        let line = self.model.get(row)?;
+        if let Some(ref url) = hl.cell_hl(line.line.get(col)?).url {
+            return Some(Link::Url(url.clone()));
+        }
+
+        let mut text = String::new();
+        let mut idx = 0;
+        for (cell_col, cell) in line.line.iter().enumerate() {
+            if cell_col == col {
+                idx = text.len();
+            }
+
+            if !cell.ch.is_empty() {
+                text.push_str(&cell.ch);
+            } else if !cell.double_width {
+                text.push(' ');
+            }
+        }
+
+        link::find_link(&text, idx)
+    }
+
     /// Get the current point where the cursor is located. Note that this isn't what you want to use
     /// if you
     pub fn cur_real_point(&self) -> ModelRect {
@@ -102,15 +130,8 @@ impl UiModel {
         self.flushed_pos
     }
 
-    pub fn put_one(
-        &mut self,
-        row: usize,
-        col: usize,
-        ch: &str,
-        double_width: bool,
-        hl: Rc<Highlight>,
-    ) {
-        self.put(row, col, ch, double_width, 1, hl);
+    pub fn put_one(&mut self, row: usize, col: usize, ch: &str, double_width: bool, hl_id: u64) {
+        self.put(row, col, ch, double_width, 1, hl_id);
     }
 
     pub fn put(
@@ -120,28 +141,27 @@ impl UiModel {
         ch: &str,
         double_width: bool,
         repeat: usize,
-        hl: Rc<Highlight>,
+        hl_id: u64,
     ) {
         let line = &mut self.model[row];
         line.dirty_line = true;
 
-        for offset in 0..repeat {
-            let cell = &mut line[col + offset];
-            cell.ch.clear();
-            cell.ch.push_str(ch);
-            cell.hl = hl.clone();
-            cell.double_width = double_width;
-            cell.dirty = true;
-        }
+        let cell = Cell {
+            ch: Grapheme::new(ch),
+            hl_id,
+            dirty: true,
+            double_width,
+        };
+        line.line[col..col + repeat].fill(cell);
     }
 
     /// Copy rows from 0 to to_row, col from 0 self.columns
     ///
     /// Don't do any validation!
     pub fn swap_rows(&mut self, target: &mut UiModel, to_row: usize) {
-        for (row_idx, line) in self.model[0..to_row + 1].iter_mut().enumerate() {
+        for (row_idx, line) in self.model[0..to_row + 1].iter().enumerate() {
             let target_row = &mut target.model[row_idx];
-            line.swap_with(target_row, 0, self.columns - 1);
+            line.copy_to(target_row, 0, self.columns - 1);
         }
     }
 
@@ -162,18 +182,15 @@ impl UiModel {
             (&mut left[from_row], &mut right[0])
         };
 
-        source_row.swap_with(target_row, left_col, right_col);
+        // Whole lines are moved along with their shaping, so they don't need to be shaped again
+        if left_col == 0 && right_col == self.columns - 1 {
+            std::mem::swap(source_row, target_row);
+        } else {
+            source_row.copy_to(target_row, left_col, right_col);
+        }
     }
 
-    pub fn scroll(
-        &mut self,
-        top: i64,
-        bot: i64,
-        left: usize,
-        right: usize,
-        count: i64,
-        default_hl: &Rc<Highlight>,
-    ) {
+    pub fn scroll(&mut self, top: i64, bot: i64, left: usize, right: usize, count: i64) {
         if count > 0 {
             for row in top..(bot - count + 1) {
                 self.swap_row(row, count, left, right);
@@ -185,39 +202,20 @@ impl UiModel {
         }
 
         if count > 0 {
-            self.clear_region(
-                (bot - count + 1) as usize,
-                bot as usize,
-                left,
-                right,
-                default_hl,
-            );
+            self.clear_region((bot - count + 1) as usize, bot as usize, left, right);
         } else {
-            self.clear_region(
-                top as usize,
-                (top - count - 1) as usize,
-                left,
-                right,
-                default_hl,
-            );
+            self.clear_region(top as usize, (top - count - 1) as usize, left, right);
         }
     }
 
-    pub fn clear(&mut self, default_hl: &Rc<Highlight>) {
+    pub fn clear(&mut self) {
         let (rows, columns) = (self.rows, self.columns);
-        self.clear_region(0, rows - 1, 0, columns - 1, default_hl);
+        self.clear_region(0, rows - 1, 0, columns - 1);
     }
 
-    fn clear_region(
-        &mut self,
-        top: usize,
-        bot: usize,
-        left: usize,
-        right: usize,
-        default_hl: &Rc<Highlight>,
-    ) {
+    fn clear_region(&mut self, top: usize, bot: usize, left: usize, right: usize) {
         for row in &mut self.model[top..bot + 1] {
-            row.clear(left, right, default_hl);
+            row.clear(left, right);
         }
     }
 
@@ -244,16 +242,15 @@ mod tests {
     fn test_scroll_area() {
         let mut model = UiModel::new(10, 20);
 
-        model.scroll(1, 5, 1, 5, 3, &Rc::new(Highlight::new()));
+        model.scroll(1, 5, 1, 5, 3);
     }
 
     #[test]
     fn test_resize_preserves_existing_cells() {
-        let hl = Rc::new(Highlight::new());
         let mut model = UiModel::new(2, 2);
 
-        model.put_one(0, 0, "a", false, hl.clone());
-        model.put_one(1, 1, "b", false, hl);
+        model.put_one(0, 0, "a", false, 0);
+        model.put_one(1, 1, "b", false, 0);
         model.set_cursor(1, 1);
         model.flush_cursor();
 
@@ -270,11 +267,10 @@ mod tests {
 
     #[test]
     fn test_resize_clamps_cursor_and_truncates_cells() {
-        let hl = Rc::new(Highlight::new());
         let mut model = UiModel::new(3, 3);
 
-        model.put_one(0, 0, "a", false, hl.clone());
-        model.put_one(2, 2, "z", false, hl);
+        model.put_one(0, 0, "a", false, 0);
+        model.put_one(2, 2, "z", false, 0);
         model.set_cursor(2, 2);
         model.flush_cursor();
 
@@ -289,10 +285,9 @@ mod tests {
 
     #[test]
     fn test_resize_noop_preserves_model_state() {
-        let hl = Rc::new(Highlight::new());
         let mut model = UiModel::new(2, 2);
 
-        model.put_one(1, 1, "x", false, hl);
+        model.put_one(1, 1, "x", false, 0);
         model.set_cursor(1, 1);
         model.flush_cursor();
         model.model[0].line[0].dirty = false;
@@ -313,4 +308,43 @@ mod tests {
         assert_eq!((1, 1), model.get_real_cursor());
         assert_eq!((1, 1), model.get_flushed_cursor());
     }
+
+    fn dirty_lines(model: &UiModel) -> Vec<usize> {
+        (0..model.rows)
+            .filter(|row| model.model[*row].dirty_line)
+            .collect()
+    }
+
+    fn clean(model: &mut UiModel) {
+        for line in model.model.iter_mut() {
+            line.dirty_line = false;
+            for cell in line.line.iter_mut() {
+                cell.dirty = false;
+            }
+        }
+    }
+
+    #[test]
+    fn test_scroll_moves_whole_lines() {
+        let mut model = UiModel::new(6, 4);
+        for row in 0..6 {
+            model.put_one(row, 0, &row.to_string(), false, 0);
+        }
+        clean(&mut model);
+
+        model.scroll(1, 4, 0, 3, 2);
+        assert_eq!("3", model.model[1].line[0].ch);
+        assert_eq!("4", model.model[2].line[0].ch);
+        assert_eq!(vec![3, 4], dirty_lines(&model));
+
+        clean(&mut model);
+        model.scroll(1, 4, 0, 3, -1);
+        assert_eq!("3", model.model[2].line[0].ch);
+        assert_eq!(vec![1], dirty_lines(&model));
+
+        // Scrolling part of the lines can change how they get shaped
+        clean(&mut model);
+        model.scroll(0, 5, 1, 3, 1);
+        assert_eq!((0..6).collect::<Vec<_>>(), dirty_lines(&model));
+    }
 }
@@ -1,23 +1,19 @@
 use std::cmp::max;
-use std::rc::Rc;
 
 use unicode_width::UnicodeWidthStr;
 
-use crate::highlight::Highlight;
 use crate::ui_model::UiModel;
 
 #[derive(Clone)]
 pub struct HighlightedRange {
-    pub highlight: Rc<Highlight>,
+    /// Id of the highlight in the `HighlightMap`
+    pub hl_id: u64,
     pub graphemes: Vec<String>,
 }
 
 impl HighlightedRange {
-    pub fn new(highlight: Rc<Highlight>, graphemes: Vec<String>) -> Self {
-        Self {
-            highlight,
-            graphemes,
-        }
+    pub fn new(hl_id: u64, graphemes: Vec<String>) -> Self {
+        Self { hl_id, graphemes }
     }
 }
 
@@ -86,7 +82,7 @@ impl ModelLayout {
         }
     }
 
-    pub fn insert_char(&mut self, ch: String, shift: bool, hl: Rc<Highlight>) {
+    pub fn insert_char(&mut self, ch: String, shift: bool, hl_id: u64) {
         if ch.is_empty() {
             return;
         }
@@ -97,7 +93,7 @@ impl ModelLayout {
             self.insert_into_lines(ch);
             self.layout_replace(0, 0);
         } else {
-            self.model.put_one(row, col, &ch, false, hl);
+            self.model.put_one(row, col, &ch, false, hl_id);
         }
     }
 
@@ -134,7 +130,7 @@ impl ModelLayout {
         let mut row_idx = row_offset;
         for highlight_ranges in lines {
             for HighlightedRange {
-                highlight: hl,
+                hl_id,
                 graphemes: ch_list,
             } in highlight_ranges
             {
@@ -146,9 +142,9 @@ impl ModelLayout {
                         row_idx += 1;
                     }
 
-                    self.model.put_one(row_idx, col_idx, ch, false, hl.clone());
+                    self.model.put_one(row_idx, col_idx, ch, false, *hl_id);
                     if ch_width > 1 {
-                        self.model.put_one(row_idx, col_idx, "", true, hl.clone());
+                        self.model.put_one(row_idx, col_idx, "", true, *hl_id);
                     }
 
                     if max_col_idx < col_idx {
@@ -159,11 +155,7 @@ impl ModelLayout {
                 }
 
                 if col_idx < self.model.columns {
-                    self.model.model[row_idx].clear(
-                        col_idx,
-                        self.model.columns - 1,
-                        &Rc::new(Highlight::new()),
-                    );
+                    self.model.model[row_idx].clear(col_idx, self.model.columns - 1);
                 }
             }
             col_idx = 0;
@@ -196,7 +188,7 @@ mod tests {
     #[test]
     fn test_count_lines() {
         let lines = vec![vec![HighlightedRange {
-            highlight: Rc::new(Highlight::new()),
+            hl_id: 0,
             graphemes: vec!["a".to_owned(); 5],
         }]];
 
@@ -208,7 +200,7 @@ mod tests {
     fn test_resize() {
         let lines = vec![
             vec![HighlightedRange {
-                highlight: Rc::new(Highlight::new()),
+                hl_id: 0,
                 graphemes: vec!["a".to_owned(); 5]
             }];
             ModelLayout::ROWS_STEP
@@ -229,13 +221,7 @@ mod tests {
 
     #[test]
     fn test_cols_filled() {
-        let lines = vec![
-            vec![HighlightedRange::new(
-                Rc::new(Highlight::new()),
-                vec!["a".to_owned(); 3]
-            )];
-            1
-        ];
+        let lines = vec![vec![HighlightedRange::new(0, vec!["a".to_owned(); 3])]; 1];
         let mut model = ModelLayout::new(5);
 
         model.layout(lines);
@@ -245,13 +231,7 @@ mod tests {
         let (cols, _) = model.size();
         assert_eq!(4, cols); // size is 3 and 4 - is with cursor position
 
-        let lines = vec![
-            vec![HighlightedRange::new(
-                Rc::new(Highlight::new()),
-                vec!["a".to_owned(); 2]
-            )];
-            1
-        ];
+        let lines = vec![vec![HighlightedRange::new(0, vec!["a".to_owned(); 2])]; 1];
 
         model.layout_append(lines);
         model.set_cursor(2);
@@ -261,18 +241,12 @@ mod tests {
 
     #[test]
     fn test_insert_shift() {
-        let lines = vec![
-            vec![HighlightedRange::new(
-                Rc::new(Highlight::new()),
-                vec!["a".to_owned(); 3]
-            )];
-            1
-        ];
+        let lines = vec![vec![HighlightedRange::new(0, vec!["a".to_owned(); 3])]; 1];
         let mut model = ModelLayout::new(5);
         model.layout(lines);
         model.set_cursor(1);
 
-        model.insert_char("b".to_owned(), true, Rc::new(Highlight::new()));
+        model.insert_char("b".to_owned(), true, 0);
 
         let (cols, _) = model.size();
         assert_eq!(4, cols);
@@ -281,18 +255,12 @@ mod tests {
 
     #[test]
     fn test_insert_no_shift() {
-        let lines = vec![
-            vec![HighlightedRange::new(
-                Rc::new(Highlight::new()),
-                vec!["a".to_owned(); 3]
-            )];
-            1
-        ];
+        let lines = vec![vec![HighlightedRange::new(0, vec!["a".to_owned(); 3])]; 1];
         let mut model = ModelLayout::new(5);
         model.layout(lines);
         model.set_cursor(1);
 
-        model.insert_char("b".to_owned(), false, Rc::new(Highlight::new()));
+        model.insert_char("b".to_owned(), false, 0);
 
         let (cols, _) = model.size();
         assert_eq!(3, cols);
@@ -301,13 +269,7 @@ mod tests {
 
     #[test]
     fn test_double_width() {
-        let lines = vec![
-            vec![HighlightedRange::new(
-                Rc::new(Highlight::new()),
-                vec!["あ".to_owned(); 3]
-            )];
-            1
-        ];
+        let lines = vec![vec![HighlightedRange::new(0, vec!["あ".to_owned(); 3])]; 1];
         let mut model = ModelLayout::new(7);
         model.layout(lines);
         model.set_cursor(1);